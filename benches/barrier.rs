@@ -0,0 +1,40 @@
+//! Rounds of `Barrier::wait`, with threads parking at once and spinning first
+//!
+//! For each thread count, this reports the mean time of a round, from the first thread
+//! arriving to the last leaving, for a barrier which never spins and one which spins
+//! `DEFAULT_SPINS` times.
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use user_sync::{Barrier, DEFAULT_SPINS};
+
+const N_ROUNDS: usize = 2_000;
+
+fn run(n_threads: usize, spins: usize) -> Duration {
+    let barrier = Arc::new(Barrier::with_spins(n_threads, spins));
+    let threads: Vec<_> = (0..n_threads).map(|_| {
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+            barrier.wait();
+            let begin = Instant::now();
+            let n_leaders = (0..N_ROUNDS).filter(|_| barrier.wait()).count();
+            (begin, Instant::now(), n_leaders)
+        })
+    }).collect();
+    let times: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert_eq!(N_ROUNDS, times.iter().map(|t| t.2).sum::<usize>());
+    let begin = times.iter().map(|t| t.0).min().unwrap();
+    let end = times.iter().map(|t| t.1).max().unwrap();
+    (end - begin) / N_ROUNDS as u32
+}
+
+fn main() {
+    for &n_threads in &[2, 4, 8, 16, 64] {
+        println!("{:>3} threads: {:>9?} per round parking, {:>9?} spinning {} times first",
+                 n_threads, run(n_threads, 0), run(n_threads, DEFAULT_SPINS), DEFAULT_SPINS);
+    }
+}
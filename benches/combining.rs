@@ -0,0 +1,59 @@
+//! `CombiningMutex` against `Mutex`, with many threads each updating a shared value
+//!
+//! For each thread count, this reports the mean time per operation, from the first thread
+//! starting to the last finishing.
+
+extern crate user_sync;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use user_sync::{CombiningMutex, Mutex};
+
+const N_OPS: usize = 200_000;
+
+trait Lock: Send + Sync + 'static {
+    fn update(&self, k: usize);
+    fn get(&self) -> u64;
+}
+
+// Work done under the lock, small enough that the handoff of the lock dominates it
+fn op(x: &mut [u64; 8], k: usize) { x[k % 8] = x[k % 8].wrapping_add(1) }
+
+impl Lock for Mutex<[u64; 8]> {
+    fn update(&self, k: usize) { op(&mut self.lock(), k) }
+    fn get(&self) -> u64 { self.lock().iter().sum() }
+}
+
+impl Lock for CombiningMutex<[u64; 8]> {
+    fn update(&self, k: usize) { self.apply(move |x| op(x, k)) }
+    fn get(&self) -> u64 { self.apply(|x| x.iter().sum()) }
+}
+
+fn run<L: Lock>(lock: L, n_threads: usize) -> Duration {
+    let n_each = N_OPS / n_threads;
+    let lock = Arc::new(lock);
+    let start = Arc::new(Barrier::new(n_threads));
+    let threads: Vec<_> = (0..n_threads).map(|k| {
+        let (lock, start) = (lock.clone(), start.clone());
+        thread::Builder::new().stack_size(0x10000).spawn(move || {
+            start.wait();
+            let begin = Instant::now();
+            for i in 0..n_each { lock.update(k + i) }
+            (begin, Instant::now())
+        }).expect("failed to spawn thread")
+    }).collect();
+    let times: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert_eq!((n_each * n_threads) as u64, lock.get());
+    let begin = times.iter().map(|t| t.0).min().unwrap();
+    let end = times.iter().map(|t| t.1).max().unwrap();
+    (end - begin) / (n_each * n_threads) as u32
+}
+
+fn main() {
+    for &n_threads in &[1, 4, 16, 64, 256, 1024] {
+        println!("{:>5} threads: Mutex {:>8?}, CombiningMutex {:>8?} per operation", n_threads,
+                 run(Mutex::new([0; 8]), n_threads), run(CombiningMutex::new([0; 8]), n_threads));
+    }
+}
@@ -0,0 +1,76 @@
+//! `FairMutex` against `Mutex`, with each of many threads taking the lock in turn
+//!
+//! For each thread count, this reports the mean time per acquisition, which is the cost of
+//! fairness, and the longest any one thread waited for the lock, which is what it buys.
+
+extern crate user_sync;
+
+use std::ptr;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use user_sync::{FairMutex, Mutex};
+
+const N_LOCKS: usize = 1_000;
+
+// Work done while holding the lock, in volatile increments, long enough that threads queue
+const N_WORK: usize = 0x40;
+
+trait Lock: Send + Sync + 'static {
+    fn with(&self, f: &mut dyn FnMut(&mut u64));
+}
+
+impl Lock for Mutex<u64> {
+    fn with(&self, f: &mut dyn FnMut(&mut u64)) { f(&mut self.lock()) }
+}
+
+impl Lock for FairMutex<u64> {
+    fn with(&self, f: &mut dyn FnMut(&mut u64)) { f(&mut self.lock()) }
+}
+
+// Return the time of the whole run and the longest wait for the lock.
+fn run<L: Lock>(lock: L, n_threads: usize) -> (Duration, Duration) {
+    let lock = Arc::new(lock);
+    let start = Arc::new(Barrier::new(n_threads + 1));
+    let threads: Vec<_> = (0..n_threads).map(|_| {
+        let (lock, start) = (lock.clone(), start.clone());
+        thread::Builder::new().stack_size(0x10000).spawn(move || {
+            start.wait();
+            let begin = Instant::now();
+            let mut max_wait = Duration::new(0, 0);
+            for _ in 0..N_LOCKS {
+                let t = Instant::now();
+                lock.with(&mut |x| {
+                    max_wait = max_wait.max(t.elapsed());
+                    for _ in 0..N_WORK {
+                        unsafe { ptr::write_volatile(x, ptr::read_volatile(x) + 1) }
+                    }
+                });
+            }
+            (begin, Instant::now(), max_wait)
+        }).expect("failed to spawn thread")
+    }).collect();
+    start.wait();
+    let times: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    let begin = times.iter().map(|t| t.0).min().unwrap();
+    let end = times.iter().map(|t| t.1).max().unwrap();
+    let max_wait = times.iter().map(|t| t.2).max().unwrap();
+    let mut n = 0;
+    lock.with(&mut |x| n = *x);
+    assert_eq!((n_threads * N_LOCKS * N_WORK) as u64, n);
+    (end - begin, max_wait)
+}
+
+fn report(name: &str, n_threads: usize, (elapsed, max_wait): (Duration, Duration)) {
+    let per_lock = elapsed / (n_threads * N_LOCKS) as u32;
+    println!("{:>9} {:>6} threads: {:>8?} per lock, longest wait {:?}",
+             name, n_threads, per_lock, max_wait);
+}
+
+fn main() {
+    for &n_threads in &[4, 64, 1024, 2048] {
+        report("Mutex", n_threads, run(Mutex::new(0), n_threads));
+        report("FairMutex", n_threads, run(FairMutex::new(0), n_threads));
+    }
+}
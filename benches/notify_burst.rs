@@ -0,0 +1,54 @@
+//! Bursts of `notify_one` outside the lock, on a plain and a coalescing `CondVar`
+//!
+//! For each number of waiters, this reports the mean time per notification and how many
+//! system calls the notifications made, which coalescing bounds by the number of waiters.
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use user_sync::{CondVar, Mutex};
+
+const N_BURSTS: usize = 1_000;
+const BURST: usize = 0x40;
+
+// Return the time per notification, and the system calls made per burst.
+fn run(cond: CondVar, n_waiters: usize) -> (Duration, f64) {
+    let shared = Arc::new((Mutex::new(false), cond));
+    let waiters: Vec<_> = (0..n_waiters).map(|_| {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            let (ref done, ref cond) = *shared;
+            let mut done = done.lock();
+            while !*done { done = cond.wait(done) }
+        })
+    }).collect();
+
+    let (ref done, ref cond) = *shared;
+    let calls = cond.stats().wake_calls;
+    let t = Instant::now();
+    for _ in 0..N_BURSTS {
+        for _ in 0..BURST { cond.notify_one(); }
+        thread::yield_now();
+    }
+    let elapsed = t.elapsed();
+    let calls = cond.stats().wake_calls - calls;
+
+    // Every waiter must yet finish.
+    *done.lock() = true;
+    cond.notify_all();
+    for w in waiters { w.join().unwrap() }
+    (elapsed / (N_BURSTS * BURST) as u32, calls as f64 / N_BURSTS as f64)
+}
+
+fn main() {
+    for &n_waiters in &[1, 4, 16, 64] {
+        let (plain_t, plain_calls) = run(CondVar::new(), n_waiters);
+        let (coal_t, coal_calls) = run(CondVar::coalescing(), n_waiters);
+        println!("{:>3} waiters, bursts of {}: plain {:>7?} per notify, {:5.1} calls per burst; \
+                  coalescing {:>7?}, {:5.1}",
+                 n_waiters, BURST, plain_t, plain_calls, coal_t, coal_calls);
+    }
+}
@@ -0,0 +1,80 @@
+//! `Mutex::fast_try_lock` against `Mutex::try_lock`
+//!
+//! This times each when the lock is free, when another thread holds it, so every try fails,
+//! and when several threads try it at once, retrying until they succeed.
+
+extern crate user_sync;
+
+use std::sync::{Arc, Barrier};
+use std::sync::atomic::{AtomicBool, Ordering as Memord};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use user_sync::{Guard, Mutex};
+
+const N_TRIES: usize = 10_000_000;
+
+type Try = for<'a> fn(&'a Mutex<u64>) -> Option<Guard<'a, u64>>;
+
+const TRIES: [(&str, Try); 2] = [("try_lock", Mutex::try_lock),
+                                 ("fast_try_lock", Mutex::fast_try_lock)];
+
+fn per_try(elapsed: Duration, n: usize) -> f64 {
+    (elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64) / n as f64
+}
+
+fn free(try_lock: Try) -> f64 {
+    let m = Mutex::new(0);
+    let t = Instant::now();
+    for _ in 0..N_TRIES { *try_lock(&m).unwrap() += 1 }
+    per_try(t.elapsed(), N_TRIES)
+}
+
+fn held(try_lock: Try) -> f64 {
+    let shared = Arc::new((Mutex::new(0), Barrier::new(2), AtomicBool::new(false)));
+    let shared_ = shared.clone();
+    let holder = thread::spawn(move || {
+        let (ref m, ref barrier, ref done) = *shared_;
+        let _g = m.lock();
+        barrier.wait();
+        while !done.load(Memord::Relaxed) { thread::sleep(Duration::from_millis(1)) }
+    });
+    let (ref m, ref barrier, ref done) = *shared;
+    barrier.wait();
+    let t = Instant::now();
+    for _ in 0..N_TRIES { assert!(try_lock(m).is_none()) }
+    let r = per_try(t.elapsed(), N_TRIES);
+    done.store(true, Memord::Relaxed);
+    holder.join().unwrap();
+    r
+}
+
+fn contended(try_lock: Try, n_threads: usize) -> f64 {
+    let n_each = N_TRIES / 10 / n_threads;
+    let shared = Arc::new((Mutex::new(0), Barrier::new(n_threads)));
+    let t = Instant::now();
+    let threads: Vec<_> = (0..n_threads).map(|_| {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            let (ref m, ref barrier) = *shared;
+            barrier.wait();
+            for _ in 0..n_each {
+                loop {
+                    if let Some(mut g) = try_lock(m) { *g += 1; break }
+                    thread::yield_now();
+                }
+            }
+        })
+    }).collect();
+    for t in threads { t.join().unwrap() }
+    let r = per_try(t.elapsed(), n_each * n_threads);
+    assert_eq!((n_each * n_threads) as u64, *shared.0.lock());
+    r
+}
+
+fn main() {
+    for &(name, try_lock) in &TRIES {
+        println!("{:>13}: free {:6.2} ns, held {:6.2} ns, 4 threads {:6.1} ns per lock",
+                 name, free(try_lock), held(try_lock), contended(try_lock, 4));
+    }
+}
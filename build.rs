@@ -0,0 +1,20 @@
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=STRAKE_SPINS");
+    println!("cargo:rerun-if-env-changed=USER_SYNC_SPINS");
+    // `USER_SYNC_SPINS` is the former name, still honoured.
+    let (var, val) = match env::var("STRAKE_SPINS") {
+        Ok(s) => ("STRAKE_SPINS", Ok(s)),
+        Err(_) => ("USER_SYNC_SPINS", env::var("USER_SYNC_SPINS")),
+    };
+    let spins: usize = match val {
+        Ok(s) => s.trim().parse().unwrap_or_else(|_| panic!("{} must be a natural number", var)),
+        Err(_) => 0x100,
+    };
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("spins");
+    write!(File::create(path).unwrap(), "{}", spins).unwrap();
+}
@@ -0,0 +1,158 @@
+use core::hint::spin_loop as cpu_relax;
+use core::sync::atomic::{AtomicUsize, Ordering as Memord};
+
+/// How long a thread spins on a contended lock before it parks
+///
+/// It relaxes, by the lock's `Backoff`, `spins` times, trying the lock after relaxing once,
+/// then twice, and so on, doubling up to `backoff` times, so the more threads contend, the less
+/// they each touch the lock meanwhile. Having spun, unless by `Backoff::None`, it yields `yields`
+/// times, trying the lock after each: on an oversubscribed system, the holder may be
+/// descheduled, and a yield may let it run and release the lock far sooner than a futex wait
+/// and wake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpinPolicy {
+    /// Times to relax
+    pub spins: usize,
+    /// Times to yield, having spun
+    pub yields: usize,
+    /// Greatest number of times to relax between attempts
+    pub backoff: usize,
+}
+
+impl SpinPolicy {
+    /// `DEFAULT_SPINS` spins, backing off up to 0x10 times, and then 3 yields, or none if
+    /// `DEFAULT_SPINS` is 0; the default policy unless `set_default_policy` says otherwise
+    pub const DEFAULT: Self = SpinPolicy {
+        spins: ::DEFAULT_SPINS,
+        yields: if 0 == ::DEFAULT_SPINS { 0 } else { 3 },
+        backoff: 0x10,
+    };
+
+    /// Park at once, as the lock is tried once more as the thread parks
+    pub const PARK: Self = SpinPolicy { spins: 0, yields: 0, backoff: 1 };
+
+    /// As this, but with no yields, e.g. for an unlocking thread waiting for another to take the
+    /// lock, for which yielding would only delay itself
+    #[cfg_attr(feature = "critical-section", allow(dead_code))]
+    #[inline] pub(crate) const fn without_yields(self) -> Self {
+        SpinPolicy { yields: 0, ..self }
+    }
+}
+
+impl Default for SpinPolicy {
+    #[inline] fn default() -> Self { SpinPolicy::DEFAULT }
+}
+
+static DEFAULT_POLICY: [AtomicUsize; 3] = [AtomicUsize::new(SpinPolicy::DEFAULT.spins),
+                                           AtomicUsize::new(SpinPolicy::DEFAULT.yields),
+                                           AtomicUsize::new(SpinPolicy::DEFAULT.backoff)];
+
+/// Set the policy by which locks spin where none is given for the lock, at first
+/// `SpinPolicy::DEFAULT`, e.g. to not spin at all on a uniprocessor, or to spin long on a
+/// server, without rebuilding the crate.
+///
+/// This is meant to be called once at startup; a thread taking a lock meanwhile may spin by a
+/// mix of the old and new policies.
+#[inline] pub fn set_default_policy(policy: SpinPolicy) {
+    DEFAULT_POLICY[0].store(policy.spins, Memord::Relaxed);
+    DEFAULT_POLICY[1].store(policy.yields, Memord::Relaxed);
+    DEFAULT_POLICY[2].store(policy.backoff, Memord::Relaxed);
+}
+
+/// Policy by which locks spin where none is given for the lock
+#[inline] pub fn default_policy() -> SpinPolicy {
+    SpinPolicy { spins: DEFAULT_POLICY[0].load(Memord::Relaxed),
+                 yields: DEFAULT_POLICY[1].load(Memord::Relaxed),
+                 backoff: DEFAULT_POLICY[2].load(Memord::Relaxed) }
+}
+
+/// What a thread does between attempts while spinning on a contended lock
+///
+/// The spin count of the `SpinPolicy` bounds how many times the thread relaxes before it parks;
+/// the strategy sets how long each lasts. So with `Yield`, the same count spins far longer than
+/// with `Pause`, as each time may wait for other threads' time slices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Hint to the processor that the thread is spinning; apt for short critical sections
+    Pause,
+    /// Let another thread run; apt for locks shared by more threads than processors
+    Yield,
+    /// Try the lock once, whatever the spin count, and then park; apt for uniprocessors
+    None,
+}
+
+#[cfg_attr(feature = "critical-section", allow(dead_code))]
+impl Backoff {
+    #[inline] pub(crate) fn spins(self, spins: usize) -> usize {
+        if Backoff::None == self { 0 } else { spins }
+    }
+
+    // Call `attempt` until it succeeds, spinning between calls, relaxing by `R`, as `policy`
+    // says; return whether it succeeded. It is called at least once, even if `policy` spins not
+    // at all, lest an uncontended lock be taken as contended.
+    #[inline] pub(crate) fn spin<R: Relax, F: FnMut() -> bool>(self, policy: SpinPolicy,
+                                                               mut attempt: F) -> bool {
+        if attempt() { return true };
+        let spins = self.spins(policy.spins);
+        let (mut k, mut gap) = (0, 1);
+        while k < spins {
+            for _ in 0..gap.min(spins - k) {
+                R::relax(self, k);
+                k += 1;
+            }
+            if attempt() { return true };
+            gap = (2 * gap).min(policy.backoff.max(1));
+        }
+        if Backoff::None == self { return false };
+        for _ in 0..policy.yields {
+            ::system::yield_now();
+            if attempt() { return true };
+        }
+        false
+    }
+
+    #[inline] pub(crate) fn relax(self) {
+        match self {
+            Backoff::Pause => cpu_relax(),
+            Backoff::Yield => ::system::yield_now(),
+            Backoff::None => (),
+        }
+    }
+}
+
+/// What a thread does each time it relaxes while spinning on a contended lock, as a type, for
+/// `Mutex<T, Futex<R>>`
+///
+/// Unlike `Backoff`, which is one of a few strategies chosen per lock, this may be any code,
+/// chosen per type, so it costs no branch; e.g. to pause longer where a pause is short:
+///
+/// ```
+/// # extern crate user_sync;
+/// use std::hint::spin_loop;
+/// use user_sync::{Backoff, Futex, Mutex, Relax};
+///
+/// #[derive(Debug)]
+/// struct Pause4;
+///
+/// impl Relax for Pause4 {
+///     fn relax(_: Backoff, _: usize) { for _ in 0..4 { spin_loop() } }
+/// }
+///
+/// # fn main() {
+/// let mutex: Mutex<u32, Futex<Pause4>> = Mutex::with_system(0);
+/// *mutex.lock() += 1;
+/// # }
+/// ```
+pub trait Relax {
+    /// Wait a while, having relaxed `k` times so far while spinning; `backoff` is that which the
+    /// lock was made with, for a strategy which defers to it.
+    fn relax(backoff: Backoff, k: usize);
+}
+
+/// Strategy which relaxes as the lock's `Backoff` says
+#[derive(Debug)]
+pub struct DefaultRelax;
+
+impl Relax for DefaultRelax {
+    #[inline] fn relax(backoff: Backoff, _: usize) { backoff.relax() }
+}
@@ -1,12 +1,96 @@
+use core::sync::atomic::AtomicU32;
+use core::time::Duration;
+
+use system_sync::{DefaultSystem, SystemBarrier};
+use time::Instant;
+
 /// Thread barrier
+///
+/// It is generic over the system barrier beneath, by default this crate's own; see
+/// `SystemSync`. Methods beyond `wait` are for the default only.
 #[derive(Debug)]
-pub struct Barrier(::system::Barrier);
+pub struct Barrier<S: SystemBarrier = DefaultSystem>(S::Barrier);
 
 impl Barrier {
     /// Make a barrier for `n` threads.
     #[inline] pub const fn new(n: usize) -> Self { Barrier(::system::Barrier::new(n)) }
 
+    /// Make a barrier for `n` threads, at which each but the last spins `spins` times, checking
+    /// whether it was released, before it parks; `new` spins `DEFAULT_SPINS` times.
+    ///
+    /// Where threads arrive nearly together, spinning spares them parking and waking.
+    #[inline] pub const fn with_spins(n: usize, spins: usize) -> Self {
+        Barrier(::system::Barrier::with_spins(n, spins))
+    }
+
+    /// As `wait`, but return which phase ended too, e.g. to tag what was done in it.
+    #[inline] pub fn wait_result(&self) -> BarrierWaitResult {
+        let (phase, leader) = self.0.wait_phase();
+        BarrierWaitResult { phase, leader }
+    }
+
+    /// As `wait`, but the last thread to arrive runs `f` before it releases the rest, so that
+    /// they see its effects, e.g. a reduction of what each thread computed in this phase.
+    ///
+    /// Threads arriving for the next phase meanwhile wait for it too. If `f` panicks, the rest
+    /// are released nonetheless.
+    #[inline] pub fn wait_with<F: FnOnce()>(&self, f: F) -> bool { self.0.wait_with(f) }
+
+    /// Wait until all threads reach the barrier, or until `deadline`.
+    /// Returns `None` if the deadline passed first, whereupon the caller is no longer counted as
+    /// waiting, so the barrier may be reused; else `Some` of what `wait` would return.
+    #[inline] pub fn wait_until(&self, deadline: Instant) -> Option<bool> {
+        self.0.wait_until(deadline.0)
+    }
+
+    /// As `wait_until`, with a deadline `timeout` from now; e.g. so that one thread missing, as
+    /// it crashed, can't hang the rest.
+    #[inline] pub fn wait_timeout(&self, timeout: Duration) -> Option<bool> {
+        self.wait_until(Instant::now() + timeout)
+    }
+
+    /// Number of threads now waiting at the barrier
+    ///
+    /// Other threads may arrive or be released meanwhile, so this is only advisory.
+    #[inline] pub fn waiting(&self) -> usize { self.0.waiting() }
+
+    /// Address of the futex word on which waiters block, e.g. to match to `futex` tracepoints
+    ///
+    /// This is only to identify the barrier; writing through it would break it.
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { self.0.futex_addr() }
+
+    /// Current phase, which advances by one, modulo 2^32, each time all threads reach the barrier
+    #[inline] pub fn phase(&self) -> u32 { self.0.phase() }
+
+    /// Release all waiting threads, each returning `false`, and restart the barrier at `phase`,
+    /// which must differ from the current phase, as waiting threads check for a change.
+    ///
+    /// No thread may arrive at the barrier meanwhile, as it may be counted toward the phase it
+    /// saw but released from the other; the caller must stop participants arriving, e.g. by one
+    /// of its own barriers, until this returns.
+    #[inline] pub fn release_to_phase(&self, phase: u32) { self.0.release_to_phase(phase) }
+}
+
+impl<S: SystemBarrier> Barrier<S> {
+    /// Make a barrier for `n` threads over that of `S`; `new` is for `DefaultSystem`.
+    #[inline] pub fn with_system(n: usize) -> Self { Barrier(S::barrier(n)) }
+
     /// Wait until all threads reach the barrier.
     /// Returns `true` in only one arbitrary thread and `false` in the rest.
-    #[inline] pub fn wait(&self) -> bool { self.0.wait() }
+    #[inline] pub fn wait(&self) -> bool { S::barrier_wait(&self.0) }
+}
+
+/// Outcome of `Barrier::wait_result`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    phase: u32,
+    leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// Whether this thread was the one arbitrary thread for which `wait` returns `true`
+    #[inline] pub fn is_leader(&self) -> bool { self.leader }
+
+    /// Phase which ended, as `Barrier::phase` returned during it
+    #[inline] pub fn phase(&self) -> u32 { self.phase }
 }
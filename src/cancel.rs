@@ -0,0 +1,30 @@
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+
+/// Cancellation flag
+///
+/// Once canceled, it stays so. A thread may block until it is canceled, and some blocking
+/// operations, such as `Mutex::lock_cancelable`, give up when it is.
+#[derive(Debug)]
+pub struct CancelToken(pub(crate) AtomicU32);
+
+impl Default for CancelToken {
+    #[inline] fn default() -> Self { CancelToken::new() }
+}
+
+impl CancelToken {
+    #[inline] pub const fn new() -> Self { CancelToken(AtomicU32::new(0)) }
+
+    /// Cancel, and unblock all threads waiting for it.
+    #[inline] pub fn cancel(&self) {
+        if 0 == self.0.swap(1, Memord::Release) { futex_wake(&self.0, !0); }
+    }
+
+    #[inline] pub fn is_canceled(&self) -> bool { 0 != self.0.load(Memord::Acquire) }
+
+    /// Block until canceled.
+    #[inline] pub fn wait(&self) {
+        while 0 == self.0.load(Memord::Acquire) { futex_wait(&self.0, 0) }
+    }
+}
@@ -0,0 +1,85 @@
+use alloc::collections::VecDeque;
+use core::fmt;
+
+use monitor::{CondVar, Mutex};
+
+/// Bounded queue, which blocks senders while full and receivers while empty
+///
+/// Any number of threads may send and receive; each value is received once, in the order sent.
+/// It is shared by reference, e.g. in an `Arc`, so it never disconnects: a receiver blocks
+/// while empty however many senders remain.
+pub struct Channel<T> {
+    queue: Mutex<VecDeque<T>>,
+    cap: usize,
+    not_empty: CondVar,
+    not_full: CondVar,
+}
+
+impl<T> fmt::Debug for Channel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Channel").field("cap", &self.cap).finish()
+    }
+}
+
+impl<T> Channel<T> {
+    /// Make a channel which holds at most `cap` values.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if `cap` is 0.
+    #[inline] pub fn new(cap: usize) -> Self {
+        assert_ne!(0, cap, "Channel made with no capacity");
+        Channel {
+            queue: Mutex::new(VecDeque::with_capacity(cap)),
+            cap,
+            not_empty: CondVar::new(),
+            not_full: CondVar::new(),
+        }
+    }
+
+    /// Send `x`, blocking while the channel is full.
+    #[inline] pub fn send(&self, x: T) {
+        let cap = self.cap;
+        let mut queue = self.not_full.wait_while(self.queue.lock(), |q| q.len() >= cap);
+        queue.push_back(x);
+        drop(queue);
+        self.not_empty.notify_one();
+    }
+
+    /// Send `x` if the channel is not full, else return it in `Err`.
+    #[inline] pub fn try_send(&self, x: T) -> Result<(), T> {
+        let mut queue = self.queue.lock();
+        if queue.len() >= self.cap { return Err(x) };
+        queue.push_back(x);
+        drop(queue);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Receive a value, blocking while the channel is empty.
+    #[inline] pub fn recv(&self) -> T {
+        let mut queue = self.not_empty.wait_while(self.queue.lock(), |q| q.is_empty());
+        let x = queue.pop_front().unwrap();
+        drop(queue);
+        self.not_full.notify_one();
+        x
+    }
+
+    /// Receive a value if the channel is not empty, else return `None`.
+    #[inline] pub fn try_recv(&self) -> Option<T> {
+        let x = self.queue.lock().pop_front();
+        if x.is_some() { self.not_full.notify_one(); }
+        x
+    }
+
+    /// Number of values in the channel
+    ///
+    /// Other threads may send or receive meanwhile, so this is only advisory.
+    #[inline] pub fn len(&self) -> usize { self.queue.lock().len() }
+
+    /// Whether the channel is empty; as `len`, only advisory
+    #[inline] pub fn is_empty(&self) -> bool { self.queue.lock().is_empty() }
+
+    /// Greatest number of values the channel holds
+    #[inline] pub fn capacity(&self) -> usize { self.cap }
+}
@@ -0,0 +1,195 @@
+//! Flat combining
+//!
+//! A thread applying an operation publishes it in a free slot, and then tries the lock; whoever
+//! holds it, the combiner, runs every published operation before releasing it, and wakes their
+//! threads. So under contention, one thread does the work of many while the guarded value stays
+//! in its cache, rather than the value and the lock moving from thread to thread. A thread which
+//! finds the lock free runs its own operation at once, before any published meanwhile, so an
+//! uncontended `apply` costs little more than a `Mutex`.
+//!
+//! A combiner which released the lock without seeing an operation published meanwhile marks it
+//! for retry and wakes its thread, which then tries the lock itself; so no operation is left
+//! waiting on a lock nobody holds.
+
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicU32, Ordering as Memord, fence};
+
+use system::{futex_wait, futex_wake};
+
+const N_SLOTS: usize = 0x40;
+
+const FREE: u32 = 0;
+const BUSY: u32 = 1;
+const PENDING: u32 = 2;
+const RETRY: u32 = 3;
+const RUNNING: u32 = 4;
+const DONE: u32 = 5;
+const PANICKED: u32 = 6;
+
+// Runs the operation at the first pointer on the value at the second
+type Run = unsafe fn(*mut (), *mut ());
+
+struct Slot {
+    state: AtomicU32,
+    op: Cell<*mut ()>,
+    run: Cell<Option<Run>>,
+}
+
+impl Slot {
+    #[inline] const fn free() -> Self {
+        Slot { state: AtomicU32::new(FREE), op: Cell::new(ptr::null_mut()), run: Cell::new(None) }
+    }
+}
+
+/// Mutex which runs contending threads' operations in one thread
+///
+/// This is for small operations on a value many threads contend for, such as a counter or a
+/// queue: `apply` waits for the operation to run, in the calling thread or another, and
+/// returns its result.
+///
+/// At most 0x40 operations are published at once; a thread which finds no free slot takes the
+/// lock as a `Mutex` would.
+pub struct CombiningMutex<T> {
+    lock: ::system::Mutex,
+    slots: [Slot; N_SLOTS],
+    valu: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for CombiningMutex<T> {}
+unsafe impl<T: Send> Sync for CombiningMutex<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for CombiningMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CombiningMutex").field("lock", &self.lock).finish()
+    }
+}
+
+impl<T> CombiningMutex<T> {
+    #[inline] pub const fn new(x: T) -> Self {
+        CombiningMutex {
+            lock: ::system::Mutex::new(),
+            slots: [const { Slot::free() }; N_SLOTS],
+            valu: UnsafeCell::new(x),
+        }
+    }
+
+    /// Run `f` on the guarded value, with exclusive access to it, and return its result.
+    ///
+    /// `f` may run in another thread, hence the `Send` bounds.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if `f` panicked; if it ran in another thread, that thread panicks too.
+    pub fn apply<R: Send, F: FnOnce(&mut T) -> R + Send>(&self, f: F) -> R {
+        let mut f = Some(f);
+        let mut r = None;
+        {
+            let mut op = |x: &mut T| r = Some((f.take().unwrap())(x));
+            // Uncontended, run the operation at once, and then any published meanwhile.
+            if self.lock.try_lock() {
+                unsafe {
+                    let combiner = Combiner(self);
+                    op(&mut *self.valu.get());
+                    mem::forget(combiner);
+                    self.combine();
+                }
+                return r.unwrap();
+            }
+            match self.claim() {
+                Some(slot) => unsafe { self.publish(slot, &mut op) },
+                None => unsafe {
+                    self.lock.lock(::default_policy());
+                    let combiner = Combiner(self);
+                    op(&mut *self.valu.get());
+                    drop(combiner);
+                },
+            }
+        }
+        r.unwrap()
+    }
+
+    #[inline] fn claim(&self) -> Option<Claim<'_>> {
+        self.slots.iter()
+            .find(|slot| slot.state.compare_exchange(FREE, BUSY, Memord::Acquire,
+                                                     Memord::Relaxed).is_ok())
+            .map(Claim)
+    }
+
+    unsafe fn publish<G: FnMut(&mut T)>(&self, slot: Claim, op: &mut G) {
+        let slot = slot.0;
+        slot.op.set(op as *mut G as *mut ());
+        slot.run.set(Some(run::<T, G>));
+        slot.state.store(PENDING, Memord::SeqCst);
+        loop {
+            fence(Memord::SeqCst);
+            if self.lock.try_lock() { self.combine() }
+            match slot.state.load(Memord::Acquire) {
+                DONE => return,
+                PANICKED => panic!("CombiningMutex operation panicked in the combining thread"),
+                RETRY => {
+                    let _ = slot.state.compare_exchange(RETRY, PENDING, Memord::SeqCst,
+                                                        Memord::SeqCst);
+                },
+                state => futex_wait(&slot.state, state),
+            }
+        }
+    }
+
+    // Run all published operations; the caller must hold the lock, which this releases.
+    unsafe fn combine(&self) {
+        let _combiner = Combiner(self);
+        for slot in self.slots.iter() {
+            if PENDING != slot.state.load(Memord::Relaxed) ||
+               slot.state.compare_exchange(PENDING, RUNNING, Memord::Acquire,
+                                           Memord::Relaxed).is_err() {
+                continue;
+            }
+            let running = Running(slot);
+            (slot.run.get().unwrap())(slot.op.get(), self.valu.get() as *mut ());
+            mem::forget(running);
+            slot.state.store(DONE, Memord::Release);
+            futex_wake(&slot.state, 1);
+        }
+    }
+}
+
+unsafe fn run<T, G: FnMut(&mut T)>(op: *mut (), x: *mut ()) {
+    (*(op as *mut G))(&mut *(x as *mut T))
+}
+
+// Held slot, freed when dropped, even by a panic
+struct Claim<'a>(&'a Slot);
+
+impl<'a> Drop for Claim<'a> {
+    #[inline] fn drop(&mut self) { self.0.state.store(FREE, Memord::Release) }
+}
+
+// Held lock, released when dropped, whereupon any operation published meanwhile is retried
+struct Combiner<'a, T: 'a>(&'a CombiningMutex<T>);
+
+impl<'a, T> Drop for Combiner<'a, T> {
+    fn drop(&mut self) {
+        self.0.lock.unlock(::default_policy());
+        fence(Memord::SeqCst);
+        for slot in self.0.slots.iter() {
+            if PENDING == slot.state.load(Memord::Relaxed) &&
+               slot.state.compare_exchange(PENDING, RETRY, Memord::Relaxed,
+                                           Memord::Relaxed).is_ok() {
+                futex_wake(&slot.state, 1);
+            }
+        }
+    }
+}
+
+// Operation being run, marked as panicked if dropped, which happens only if it panics
+struct Running<'a>(&'a Slot);
+
+impl<'a> Drop for Running<'a> {
+    #[inline] fn drop(&mut self) {
+        self.0.state.store(PANICKED, Memord::Release);
+        futex_wake(&self.0.state, 1);
+    }
+}
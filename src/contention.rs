@@ -0,0 +1,52 @@
+//! Sampled backtraces of contention
+//!
+//! Every so often a thread is about to park on a lock, its backtrace is recorded with the lock,
+//! for `dump_hot_locks` to show where contention arises.
+
+use core::cmp::Reverse;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering as Memord};
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::sync::Mutex as StdMutex;
+use std::vec::Vec;
+
+const MAX_SAMPLES: usize = 0x400;
+
+static PERIOD: AtomicUsize = AtomicUsize::new(0x40);
+static N_PARKS: AtomicUsize = AtomicUsize::new(0);
+static SAMPLES: StdMutex<VecDeque<(usize, Backtrace)>> = StdMutex::new(VecDeque::new());
+
+/// Record the backtrace of every `n`th thread to park on a lock, or none if `n` is 0. By
+/// default, `n` is 0x40.
+///
+/// Only the latest 0x400 samples are kept.
+#[inline] pub fn set_contention_sample_period(n: usize) { PERIOD.store(n, Memord::Relaxed) }
+
+/// Print the sampled backtraces to standard error, grouped by lock, most often sampled first.
+///
+/// Each lock is identified by the address of its futex word.
+pub fn dump_hot_locks() {
+    let samples = SAMPLES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut locks: Vec<(usize, Vec<&Backtrace>)> = Vec::new();
+    for &(addr, ref bt) in samples.iter() {
+        match locks.iter_mut().find(|&&mut (a, _)| a == addr) {
+            Some(&mut (_, ref mut bts)) => bts.push(bt),
+            None => locks.push((addr, vec![bt])),
+        }
+    }
+    locks.sort_by_key(|(_, bts)| Reverse(bts.len()));
+    for (addr, bts) in locks {
+        eprintln!("Lock {:#x}: {} sampled parks", addr, bts.len());
+        for bt in bts { eprintln!("{}", bt) }
+    }
+}
+
+#[inline]
+pub fn park(lock: *const AtomicU32) {
+    let period = PERIOD.load(Memord::Relaxed);
+    if 0 == period || !N_PARKS.fetch_add(1, Memord::Relaxed).is_multiple_of(period) { return }
+    let bt = Backtrace::force_capture();
+    let mut samples = SAMPLES.lock().unwrap_or_else(|e| e.into_inner());
+    if samples.len() >= MAX_SAMPLES { samples.pop_front(); }
+    samples.push_back((lock as usize, bt));
+}
@@ -0,0 +1,92 @@
+//! Deadlock detection
+//!
+//! Each thread about to block on a `Mutex` records which it awaits in a global table, and then
+//! follows the chain from the owner of that lock to the lock its owner awaits, and so on; if the
+//! chain comes back to the thread itself, its wait would never end, so it panics instead.
+//!
+//! A lock's owner is set after it is taken and cleared before it is released, and a thread's
+//! entry is only added and removed under the table lock, so any cycle found is real.
+
+use core::cell::UnsafeCell;
+use core::hint::spin_loop as cpu_relax;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering as Memord};
+
+use system::{Mutex, gettid};
+
+const MAX_THREADS: usize = 0x100;
+const MAX_CYCLE: usize = 0x10;
+
+#[derive(Clone, Copy)]
+struct Waiter {
+    tid: u32,
+    lock: *const Mutex,
+}
+
+struct Table {
+    lock: AtomicBool,
+    waiters: UnsafeCell<[Waiter; MAX_THREADS]>,
+}
+
+unsafe impl Sync for Table {}
+
+static TABLE: Table = Table {
+    lock: AtomicBool::new(false),
+    waiters: UnsafeCell::new([Waiter { tid: 0, lock: ptr::null() }; MAX_THREADS]),
+};
+
+#[inline]
+fn with_table<A, F: FnOnce(&mut [Waiter; MAX_THREADS]) -> A>(f: F) -> A {
+    while TABLE.lock.compare_exchange_weak(false, true, Memord::Acquire, Memord::Relaxed)
+                    .is_err() { cpu_relax() }
+    let a = f(unsafe { &mut *TABLE.waiters.get() });
+    TABLE.lock.store(false, Memord::Release);
+    a
+}
+
+/// Record that the calling thread is about to block on `lock`.
+///
+/// # Panics
+///
+/// Panicks if the wait would close a cycle; the message lists the threads on it, starting with
+/// the calling thread.
+pub fn block(lock: &Mutex) {
+    let tid = gettid();
+    let mut cycle = [0; MAX_CYCLE];
+    let n = with_table(|waiters| {
+        if let Some(w) = waiters.iter_mut().find(|w| 0 == w.tid) {
+            *w = Waiter { tid, lock };
+        } else { return 0 }
+
+        let mut lock: *const Mutex = lock;
+        cycle[0] = tid;
+        for n in 1..MAX_THREADS {
+            let owner = unsafe { (*lock).owner() };
+            if 0 == owner { return 0 };
+            if tid == owner {
+                if let Some(w) = waiters.iter_mut().find(|w| tid == w.tid) { w.tid = 0 };
+                return n;
+            }
+            if let Some(c) = cycle.get_mut(n) { *c = owner };
+            match waiters.iter().find(|w| owner == w.tid) {
+                Some(w) => lock = w.lock,
+                None => return 0,
+            }
+        }
+        0
+    });
+    if n > 0 {
+        panic!("Deadlock: each thread of {:?} waits on a lock held by the next",
+               &cycle[..n.min(MAX_CYCLE)]);
+    }
+}
+
+/// Record that the calling thread is no longer blocked.
+pub fn unblock() {
+    let tid = gettid();
+    with_table(|waiters| {
+        if let Some(w) = waiters.iter_mut().find(|w| tid == w.tid) {
+            *w = Waiter { tid: 0, lock: ptr::null() };
+        }
+    });
+}
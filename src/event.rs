@@ -0,0 +1,107 @@
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+use wait_any::Waitable;
+
+/// Event which, once set, lets every thread through until reset
+///
+/// This is a manual-reset event: `set` unblocks all waiting threads, and `wait` returns at once
+/// until `reset`.
+#[derive(Debug)]
+pub struct Event(AtomicU32);
+
+impl Event {
+    /// Make an event, set or not.
+    #[inline] pub const fn new(set: bool) -> Self { Event(AtomicU32::new(set as u32)) }
+
+    /// Set the event, unblocking all waiting threads.
+    #[inline] pub fn set(&self) {
+        if 0 == self.0.swap(1, Memord::Release) { futex_wake(&self.0, !0); }
+    }
+
+    /// Clear the event, so that threads block at it again.
+    #[inline] pub fn reset(&self) { self.0.store(0, Memord::Relaxed) }
+
+    /// Block until the event is set.
+    #[inline] pub fn wait(&self) {
+        while 0 == self.0.load(Memord::Acquire) { futex_wait(&self.0, 0) }
+    }
+
+    /// Whether the event is set
+    #[inline] pub fn is_set(&self) -> bool { 0 != self.0.load(Memord::Acquire) }
+}
+
+impl Waitable for Event {
+    #[inline] fn poll(&self) -> Result<(), (&AtomicU32, u32)> {
+        if self.is_set() { Ok(()) } else { Err((&self.0, 0)) }
+    }
+}
+
+/// Event which lets one thread through each time it is set
+///
+/// This is an auto-reset event: `set` unblocks one waiting thread, which consumes it, so the
+/// next blocks again; if none are waiting, it stays set until one comes. Setting it while set
+/// does nothing, so several sets before any wait let only one thread through.
+#[derive(Debug)]
+pub struct AutoResetEvent(AtomicU32);
+
+impl AutoResetEvent {
+    /// Make an event, set or not.
+    #[inline] pub const fn new(set: bool) -> Self { AutoResetEvent(AtomicU32::new(set as u32)) }
+
+    /// Set the event, unblocking a waiting thread, if any.
+    #[inline] pub fn set(&self) {
+        if 0 == self.0.swap(1, Memord::Release) { futex_wake(&self.0, 1); }
+    }
+
+    /// Block until the event is set, consuming it.
+    #[inline] pub fn wait(&self) {
+        while !self.try_wait() { futex_wait(&self.0, 0) }
+    }
+
+    /// Consume the event if it is set, else return `false`.
+    #[inline] pub fn try_wait(&self) -> bool {
+        self.0.compare_exchange(1, 0, Memord::Acquire, Memord::Relaxed).is_ok()
+    }
+}
+
+impl Waitable for AutoResetEvent {
+    #[inline] fn poll(&self) -> Result<(), (&AtomicU32, u32)> {
+        if self.try_wait() { Ok(()) } else { Err((&self.0, 0)) }
+    }
+}
+
+/// Flag raised once, which threads wait for, e.g. to learn that shutdown was requested or
+/// initialization is complete
+///
+/// As an `Event` which can't be reset: once `notify` raises it, `wait` returns at once.
+#[derive(Debug)]
+pub struct Flag(AtomicU32);
+
+impl Default for Flag {
+    #[inline] fn default() -> Self { Flag::new() }
+}
+
+impl Flag {
+    /// Make an unraised flag.
+    #[inline] pub const fn new() -> Self { Flag(AtomicU32::new(0)) }
+
+    /// Raise the flag, unblocking all waiting threads; if it was raised already, do nothing.
+    #[inline] pub fn notify(&self) {
+        if 0 == self.0.swap(1, Memord::Release) { futex_wake(&self.0, !0); }
+    }
+
+    /// Block until the flag is raised.
+    #[inline] pub fn wait(&self) {
+        while 0 == self.0.load(Memord::Acquire) { futex_wait(&self.0, 0) }
+    }
+
+    /// Whether the flag was raised
+    #[inline] pub fn is_notified(&self) -> bool { 0 != self.0.load(Memord::Acquire) }
+}
+
+impl Waitable for Flag {
+    #[inline] fn poll(&self) -> Result<(), (&AtomicU32, u32)> {
+        if self.is_notified() { Ok(()) } else { Err((&self.0, 0)) }
+    }
+}
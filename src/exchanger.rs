@@ -0,0 +1,126 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::time::Duration;
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wait_until, futex_wake, wait_failed};
+use time::Instant;
+
+// EMPTY: no thread waiting; the next to come loads its value in `first`, meanwhile LOADING, and
+// then waits, WAITING. The next to come then swaps its value in, meanwhile TAKING, and leaves
+// the state PAIRED, whereupon the first takes the value in `second` and empties it. Threads which
+// come in between wait for it to be empty.
+const EMPTY: u32 = 0;
+const LOADING: u32 = 1;
+const WAITING: u32 = 2;
+const TAKING: u32 = 3;
+const PAIRED: u32 = 4;
+
+/// Point where two threads meet and swap values
+///
+/// E.g. a producer hands a full buffer to a consumer and receives an empty one. Threads pair up
+/// in order of arrival, two at a time.
+pub struct Exchanger<T> {
+    state: AtomicU32,
+    first: UnsafeCell<Option<T>>,
+    second: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Send for Exchanger<T> {}
+unsafe impl<T: Send> Sync for Exchanger<T> {}
+
+impl<T> fmt::Debug for Exchanger<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Exchanger").field("state", &self.state).finish()
+    }
+}
+
+impl<T> Default for Exchanger<T> {
+    #[inline] fn default() -> Self { Exchanger::new() }
+}
+
+impl<T> Exchanger<T> {
+    #[inline] pub const fn new() -> Self {
+        Exchanger {
+            state: AtomicU32::new(EMPTY),
+            first: UnsafeCell::new(None),
+            second: UnsafeCell::new(None),
+        }
+    }
+
+    /// Give `x` to the next thread to call this, blocking until it does, and return its value.
+    #[inline] pub fn exchange(&self, x: T) -> T {
+        match self.exchange_deadline(x, None) { Ok(y) => y, Err(_) => unreachable!() }
+    }
+
+    /// As `exchange`, but give up after `timeout`, returning `x` in `Err`.
+    #[inline] pub fn exchange_timeout(&self, x: T, timeout: Duration) -> Result<T, T> {
+        self.exchange_deadline(x, Some(Instant::now() + timeout))
+    }
+
+    /// As `exchange`, but give up at `deadline`, returning `x` in `Err`.
+    #[inline] pub fn exchange_until(&self, x: T, deadline: Instant) -> Result<T, T> {
+        self.exchange_deadline(x, Some(deadline))
+    }
+
+    fn exchange_deadline(&self, x: T, deadline: Option<Instant>) -> Result<T, T> {
+        let park = |state| match deadline {
+            None => { futex_wait(&self.state, state); true },
+            Some(deadline) => futex_wait_until(&self.state, state, deadline.0)
+                                  .unwrap_or_else(|e| wait_failed(e)),
+        };
+        loop {
+            match self.state.compare_exchange(EMPTY, LOADING, Memord::Acquire, Memord::Acquire)
+                            .unwrap_or_else(|state| state) {
+                EMPTY => return unsafe { self.wait_for_partner(x, deadline) },
+                WAITING => {
+                    if self.state.compare_exchange(WAITING, TAKING, Memord::Acquire,
+                                                   Memord::Relaxed).is_err() {
+                        continue;
+                    }
+                    unsafe {
+                        let y = (*self.first.get()).take().unwrap();
+                        *self.second.get() = Some(x);
+                        self.state.store(PAIRED, Memord::Release);
+                        futex_wake(&self.state, !0);
+                        return Ok(y);
+                    }
+                },
+                state => if !park(state) { return Err(x) },
+            }
+        }
+    }
+
+    // Having loaded the state, load `x`, and wait for a partner, or for `deadline`, whereupon
+    // withdraw `x` unless a partner took it meanwhile.
+    unsafe fn wait_for_partner(&self, x: T, deadline: Option<Instant>) -> Result<T, T> {
+        *self.first.get() = Some(x);
+        self.state.store(WAITING, Memord::Release);
+        let mut deadline = deadline;
+        loop {
+            match self.state.load(Memord::Acquire) {
+                PAIRED => {
+                    let y = (*self.second.get()).take().unwrap();
+                    self.state.store(EMPTY, Memord::Release);
+                    futex_wake(&self.state, !0);
+                    return Ok(y);
+                },
+                state => match deadline {
+                    Some(t) if !futex_wait_until(&self.state, state, t.0)
+                                   .unwrap_or_else(|e| wait_failed(e)) => {
+                        if self.state.compare_exchange(WAITING, LOADING, Memord::Acquire,
+                                                       Memord::Relaxed).is_ok() {
+                            let x = (*self.first.get()).take().unwrap();
+                            self.state.store(EMPTY, Memord::Release);
+                            futex_wake(&self.state, !0);
+                            return Err(x);
+                        }
+                        deadline = None;
+                    },
+                    Some(_) => (),
+                    None => futex_wait(&self.state, state),
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+/// Mutual exclusionary primitive which grants the lock in order of request
+///
+/// Where `Mutex` lets an arriving thread take the lock ahead of those waiting, and wakes them
+/// to race for it, this queues waiters and hands the lock to the first on release, so under
+/// contention each release wakes exactly one thread, and none waits on more than those ahead of
+/// it. This bounds the latency of each acquisition at some cost of throughput, as the lock is
+/// never taken by a thread already running.
+#[derive(Debug)]
+pub struct FairMutex<T: ?Sized> {
+    lock: ::system::FairMutex,
+    valu: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for FairMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for FairMutex<T> {}
+
+impl<T> FairMutex<T> {
+    #[inline] pub const fn new(x: T) -> Self {
+        FairMutex {
+            lock: ::system::FairMutex::new(),
+            valu: UnsafeCell::new(x),
+        }
+    }
+}
+
+impl<T: ?Sized> FairMutex<T> {
+    /// Take an exclusive reference to the guarded value, blocking until all threads which
+    /// requested it earlier have released it.
+    #[inline] pub fn lock(&self) -> FairGuard<'_, T> {
+        self.lock.lock();
+        FairGuard { lock: &self.lock, valu: unsafe { &mut *self.valu.get() } }
+    }
+
+    /// Take an exclusive reference to the guarded value, returning `None` if another thread is
+    /// already holding it.
+    #[inline] pub fn try_lock(&self) -> Option<FairGuard<'_, T>> {
+        if self.lock.try_lock() {
+            Some(FairGuard { lock: &self.lock, valu: unsafe { &mut *self.valu.get() } })
+        } else { None }
+    }
+}
+
+/// Exclusive reference to `FairMutex`-guarded value
+#[derive(Debug)]
+pub struct FairGuard<'a, T: ?Sized + 'a> {
+    lock: &'a ::system::FairMutex,
+    valu: &'a mut T,
+}
+
+impl<'a, T: ?Sized> Deref for FairGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { self.valu }
+}
+
+impl<'a, T: ?Sized> DerefMut for FairGuard<'a, T> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { self.valu }
+}
+
+impl<'a, T: ?Sized> Drop for FairGuard<'a, T> {
+    #[inline] fn drop(&mut self) { self.lock.unlock() }
+}
@@ -0,0 +1,250 @@
+//! Futex system calls, with their errors
+//!
+//! These are the calls beneath `raw`, for building further primitives, but rather than fold
+//! each outcome into what the crate's own primitives need, they return that of the kernel: the
+//! number of threads woken or moved, or why the call failed, e.g. that the word no longer held
+//! the expected value, or that a signal interrupted the wait.
+//!
+//! ```
+//! # extern crate user_sync;
+//! # use std::sync::atomic::AtomicU32;
+//! # use std::time::Duration;
+//! use user_sync::futex::{self, FutexError};
+//!
+//! # fn main() {
+//! let word = AtomicU32::new(1);
+//! assert_eq!(Err(FutexError::Again), futex::wait(&word, 0));
+//! assert_eq!(Err(FutexError::TimedOut),
+//!            futex::wait_timeout(&word, 1, Duration::from_millis(1)));
+//! assert_eq!(Ok(0), futex::wake(&word, 1));
+//! # }
+//! ```
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::AtomicU32;
+use core::time::Duration;
+
+use linux::{EAGAIN, EINTR, EINVAL, ETIMEDOUT, FUTEX_CMP_REQUEUE, FUTEX_PRIVATE_FLAG,
+            FUTEX_REQUEUE, FUTEX_WAIT, FUTEX_WAKE, FUTEX_WAKE_BITSET, FUTEX_WAKE_OP, Timespec,
+            wake_count};
+#[cfg(not(feature = "critical-section"))]
+use linux::{CLOCK_MONOTONIC, FUTEX_BITSET_MATCH_ANY, FUTEX_WAIT_BITSET};
+#[cfg(not(feature = "critical-section"))] use time::Instant;
+
+pub use linux::{futex_op, FUTEX_OP_SET, FUTEX_OP_ADD, FUTEX_OP_OR, FUTEX_OP_ANDN, FUTEX_OP_XOR,
+                FUTEX_OP_OPARG_SHIFT,
+                FUTEX_OP_CMP_EQ, FUTEX_OP_CMP_NE, FUTEX_OP_CMP_LT, FUTEX_OP_CMP_LE,
+                FUTEX_OP_CMP_GT, FUTEX_OP_CMP_GE};
+
+/// Error of a futex system call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FutexError {
+    /// The word didn't hold the value given, so the thread didn't block, or none was moved;
+    /// `EAGAIN`
+    Again,
+    /// A signal handler ran while the thread was blocked; `EINTR`
+    Interrupted,
+    /// The timeout or deadline passed; `ETIMEDOUT`
+    TimedOut,
+    /// An argument was invalid, e.g. a word not 4-aligned; `EINVAL`
+    Invalid,
+    /// Another error, by its `errno`
+    Other(usize),
+}
+
+impl FutexError {
+    #[inline] fn from_errno(errno: usize) -> Self {
+        match errno {
+            EAGAIN => FutexError::Again,
+            EINTR => FutexError::Interrupted,
+            ETIMEDOUT => FutexError::TimedOut,
+            EINVAL => FutexError::Invalid,
+            errno => FutexError::Other(errno),
+        }
+    }
+
+    /// The `errno` of the error
+    #[inline] pub fn errno(self) -> usize {
+        match self {
+            FutexError::Again => EAGAIN,
+            FutexError::Interrupted => EINTR,
+            FutexError::TimedOut => ETIMEDOUT,
+            FutexError::Invalid => EINVAL,
+            FutexError::Other(errno) => errno,
+        }
+    }
+}
+
+impl fmt::Display for FutexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FutexError::Again => f.write_str("futex word changed"),
+            FutexError::Interrupted => f.write_str("futex wait interrupted"),
+            FutexError::TimedOut => f.write_str("futex wait timed out"),
+            FutexError::Invalid => f.write_str("invalid futex argument"),
+            FutexError::Other(errno) => write!(f, "futex error {}", errno),
+        }
+    }
+}
+
+// The kernel returns an error as its negated `errno`, which is at most 4095.
+#[inline] pub(crate) fn check(r: usize) -> Result<usize, FutexError> {
+    if r > !0 - 4095 { Err(FutexError::from_errno(r.wrapping_neg())) } else { Ok(r) }
+}
+
+/// Block while `*f == val`, until woken.
+///
+/// The comparison and the sleep are atomic with respect to `wake` on the same word. It may
+/// return spuriously, so the caller must recheck its condition.
+#[inline]
+pub fn wait(f: &AtomicU32, val: u32) -> Result<(), FutexError> {
+    check(unsafe {
+        syscall!(FUTEX, f as *const _, FUTEX_WAIT | FUTEX_PRIVATE_FLAG, val, 0)
+    }).map(|_| ())
+}
+
+/// As `wait`, but for at most `timeout`.
+#[inline]
+pub fn wait_timeout(f: &AtomicU32, val: u32, timeout: Duration) -> Result<(), FutexError> {
+    let ts = Timespec::from(timeout);
+    check(unsafe {
+        syscall!(FUTEX, f as *const _, FUTEX_WAIT | FUTEX_PRIVATE_FLAG, val, &ts as *const _)
+    }).map(|_| ())
+}
+
+/// As `wait`, but until at most `deadline`.
+///
+/// The deadline is absolute, so a caller which waits again after a spurious return needn't
+/// recompute a timeout.
+#[cfg(not(feature = "critical-section"))]
+#[inline]
+pub fn wait_until(f: &AtomicU32, val: u32, deadline: Instant) -> Result<(), FutexError> {
+    wait_bitset(f, val, Some(deadline), FUTEX_BITSET_MATCH_ANY)
+}
+
+/// As `wait_until`, or `wait` if `deadline` is `None`, but only woken by `wake_bitset` with a
+/// `bitset` which shares a bit with this one, or by `wake`; e.g. to wake only readers or only
+/// writers of a lock whose threads all wait on one word.
+///
+/// It fails with `Invalid` if `bitset` is 0.
+#[cfg(not(feature = "critical-section"))]
+#[inline]
+pub fn wait_bitset(f: &AtomicU32, val: u32, deadline: Option<Instant>,
+                   bitset: u32) -> Result<(), FutexError> {
+    let ts = deadline.map(|t| Timespec::from_nanos(t.0));
+    let ts = ts.as_ref().map_or(ptr::null(), |ts| ts as *const Timespec);
+    check(unsafe {
+        syscall!(FUTEX, f as *const _, FUTEX_WAIT_BITSET | FUTEX_PRIVATE_FLAG, val, ts, 0, bitset)
+    }).map(|_| ())
+}
+
+/// Unblock at most `n` threads waiting on `f`; return how many were unblocked.
+#[inline]
+pub fn wake(f: &AtomicU32, n: usize) -> Result<usize, FutexError> {
+    check(unsafe {
+        syscall!(FUTEX, f as *const _, FUTEX_WAKE | FUTEX_PRIVATE_FLAG, wake_count(n))
+    })
+}
+
+/// Unblock at most `n` threads waiting on `f` by `wait_bitset` with a bitset which shares a bit
+/// with `bitset`, or by another wait; return how many were unblocked.
+#[inline]
+pub fn wake_bitset(f: &AtomicU32, n: usize, bitset: u32) -> Result<usize, FutexError> {
+    check(unsafe {
+        syscall!(FUTEX, f as *const _, FUTEX_WAKE_BITSET | FUTEX_PRIVATE_FLAG, wake_count(n), 0,
+                 0, bitset)
+    })
+}
+
+/// Atomically modify `f2` by `op`, made by `futex_op`, unblock at most `n1` threads waiting on
+/// `f1`, and, if the old value of `f2` satisfies the comparison of `op`, at most `n2` waiting
+/// on `f2`; return how many threads were unblocked in total.
+#[inline]
+pub fn wake_op(f1: &AtomicU32, n1: usize, f2: &AtomicU32, n2: usize,
+               op: u32) -> Result<usize, FutexError> {
+    check(unsafe {
+        syscall!(FUTEX, f1 as *const _, FUTEX_WAKE_OP | FUTEX_PRIVATE_FLAG, wake_count(n1),
+                 wake_count(n2), f2 as *const _, op)
+    })
+}
+
+/// Unblock at most `n_wake` threads waiting on `f1`, and move at most `n_requeue` more to wait
+/// on `f2` instead; return how many were unblocked and moved in total.
+///
+/// A thread may have changed `f1` meanwhile, so `cmp_requeue` is usually wanted instead.
+#[inline]
+pub fn requeue(f1: &AtomicU32, n_wake: usize,
+               f2: &AtomicU32, n_requeue: usize) -> Result<usize, FutexError> {
+    check(unsafe {
+        syscall!(FUTEX, f1 as *const _, FUTEX_REQUEUE | FUTEX_PRIVATE_FLAG, wake_count(n_wake),
+                 wake_count(n_requeue), f2 as *const _)
+    })
+}
+
+/// As `requeue`, but only if `*f1 == val`, else fail with `Again`.
+///
+/// A moved thread stays blocked until woken on `f2`, whereupon it returns from its wait on `f1`.
+#[inline]
+pub fn cmp_requeue(f1: &AtomicU32, val: u32, n_wake: usize,
+                   f2: &AtomicU32, n_requeue: usize) -> Result<usize, FutexError> {
+    check(unsafe {
+        syscall!(FUTEX, f1 as *const _, FUTEX_CMP_REQUEUE | FUTEX_PRIVATE_FLAG,
+                 wake_count(n_wake), wake_count(n_requeue), f2 as *const _, val)
+    })
+}
+
+/// Word for `wait_any` to wait on, with the value while which to block
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct WaitV<'a> {
+    val: u64,
+    uaddr: u64,
+    flags: u32,
+    reserved: u32,
+    word: PhantomData<&'a AtomicU32>,
+}
+
+// Size of the words, 32 bits, as those of the other calls
+const FUTEX2_SIZE_U32: u32 = 0x02;
+
+impl<'a> WaitV<'a> {
+    #[inline] pub fn new(f: &'a AtomicU32, val: u32) -> Self {
+        WaitV { val: val as u64, uaddr: f as *const _ as usize as u64,
+                flags: FUTEX2_SIZE_U32 | FUTEX_PRIVATE_FLAG as u32, reserved: 0,
+                word: PhantomData }
+    }
+}
+
+/// Greatest number of words `wait_any` waits on at once
+pub const WAITV_MAX: usize = 128;
+
+#[cfg(target_arch = "mips")] const NR_FUTEX_WAITV: usize = 4449;
+#[cfg(target_arch = "mips64")] const NR_FUTEX_WAITV: usize = 5449;
+#[cfg(not(any(target_arch = "mips", target_arch = "mips64")))] const NR_FUTEX_WAITV: usize = 449;
+
+/// Block while each word of `ws` holds its value, until any is woken; return the index of that
+/// which was woken.
+///
+/// It fails with `Again` if any word doesn't hold its value, and with `Other(ENOSYS)`, 38, on
+/// kernels older than 5.16, which lack the call, or `Invalid` if `ws` is empty or longer than
+/// `WAITV_MAX`. As `wait`, it may return spuriously.
+#[inline]
+pub fn wait_any(ws: &[WaitV]) -> Result<usize, FutexError> {
+    check(unsafe {
+        ::syscall::syscall5(NR_FUTEX_WAITV, ws.as_ptr() as usize, ws.len(), 0,
+                            ptr::null::<Timespec>() as usize, 0)
+    })
+}
+
+/// As `wait_any`, but until at most `deadline`.
+#[cfg(not(feature = "critical-section"))]
+#[inline]
+pub fn wait_any_until(ws: &[WaitV], deadline: Instant) -> Result<usize, FutexError> {
+    let ts = Timespec::from_nanos(deadline.0);
+    check(unsafe {
+        ::syscall::syscall5(NR_FUTEX_WAITV, ws.as_ptr() as usize, ws.len(), 0,
+                            &ts as *const _ as usize, CLOCK_MONOTONIC)
+    })
+}
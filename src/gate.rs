@@ -0,0 +1,66 @@
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+use wait_any::Waitable;
+
+/// Gate which lets through a given number of threads and then closes
+///
+/// Each `open_for(n)` lets `n` more threads through: first those waiting, up to `n`, and then,
+/// if fewer were waiting, the next to arrive, until `n` have passed; the budget does not
+/// expire.
+#[derive(Debug)]
+pub struct CountingGate(AtomicU32);
+
+impl Default for CountingGate {
+    #[inline] fn default() -> Self { CountingGate::new() }
+}
+
+impl CountingGate {
+    /// Make a closed gate.
+    #[inline] pub const fn new() -> Self { CountingGate(AtomicU32::new(0)) }
+
+    /// Let `n` more threads through.
+    #[inline] pub fn open_for(&self, n: u32) {
+        self.0.fetch_add(n, Memord::Release);
+        futex_wake(&self.0, n as usize);
+    }
+
+    /// Block until let through.
+    #[inline] pub fn wait(&self) {
+        let mut m = self.0.load(Memord::Relaxed);
+        loop {
+            if 0 == m {
+                futex_wait(&self.0, 0);
+                m = self.0.load(Memord::Relaxed);
+                continue;
+            }
+            match self.0.compare_exchange_weak(m, m - 1, Memord::Acquire, Memord::Relaxed) {
+                Ok(_) => return,
+                Err(n) => m = n,
+            }
+        }
+    }
+
+    /// Pass if the gate is open, else return `false`.
+    #[inline] pub fn try_pass(&self) -> bool {
+        let mut m = self.0.load(Memord::Relaxed);
+        while 0 != m {
+            match self.0.compare_exchange_weak(m, m - 1, Memord::Acquire, Memord::Relaxed) {
+                Ok(_) => return true,
+                Err(n) => m = n,
+            }
+        }
+        false
+    }
+
+    /// Number of threads which may yet pass
+    ///
+    /// Other threads may pass or open the gate meanwhile, so this is only advisory.
+    #[inline] pub fn remaining(&self) -> u32 { self.0.load(Memord::Relaxed) }
+}
+
+impl Waitable for CountingGate {
+    #[inline] fn poll(&self) -> Result<(), (&AtomicU32, u32)> {
+        if self.try_pass() { Ok(()) } else { Err((&self.0, 0)) }
+    }
+}
@@ -0,0 +1,77 @@
+//! Uniprocessor backend
+//!
+//! A lock is held with interrupts disabled, by `critical_section`, so nothing else can run on
+//! the processor to contend for it; hence nothing need spin nor park.
+//! Locks must be released in the reverse order they were taken, else interrupts would be
+//! restored early.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use critical_section::{self as cs, RestoreState};
+
+use backoff::{Backoff, SpinPolicy};
+
+pub struct Mutex {
+    locked: UnsafeCell<bool>,
+    state: UnsafeCell<RestoreState>,
+}
+
+unsafe impl Sync for Mutex {}
+
+impl fmt::Debug for Mutex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mutex").field("locked", unsafe { &*self.locked.get() }).finish()
+    }
+}
+
+impl Mutex {
+    #[inline] pub const fn with_backoff(_: Backoff) -> Self { Mutex::new() }
+
+    #[inline] pub const fn new() -> Self {
+        Mutex {
+            locked: UnsafeCell::new(false),
+            state: UnsafeCell::new(RestoreState::invalid()),
+        }
+    }
+
+    #[inline] pub fn lock(&self, _: SpinPolicy) {
+        if !self.try_lock() {
+            panic!("Mutex locked while already locked; nothing else could ever unlock it");
+        }
+    }
+
+    #[inline] pub fn unlock(&self, _: SpinPolicy) { unsafe {
+        debug_assert!(*self.locked.get(),
+                      "Mutex unlocked while not locked; was a guard dropped twice?");
+        *self.locked.get() = false;
+        cs::release(*self.state.get());
+    } }
+
+    // No thread can wait, so there is none to hand the lock to.
+    #[inline] pub fn unlock_fair(&self) { self.unlock(SpinPolicy::PARK) }
+
+    #[inline] pub fn bump(&self, _: SpinPolicy) {}
+
+    #[inline] pub fn try_lock(&self) -> bool { unsafe {
+        let state = cs::acquire();
+        if *self.locked.get() {
+            cs::release(state);
+            return false;
+        }
+        *self.locked.get() = true;
+        *self.state.get() = state;
+        true
+    } }
+
+    #[inline] pub fn fast_try_lock(&self) -> bool { self.try_lock() }
+
+    #[inline] pub fn adopt(&self) {}
+
+    #[inline] pub fn is_locked(&self) -> bool { unsafe { *self.locked.get() } }
+
+    #[inline] pub fn is_contended(&self) -> bool { false }
+
+    #[inline] pub fn owner(&self) -> u32 { 0 }
+}
+
+#[inline] pub fn yield_now() {}
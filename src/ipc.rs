@@ -0,0 +1,170 @@
+//! Locks, condition variables and barriers for memory shared between processes
+//!
+//! These wait on shared futexes, which the kernel keys by the backing page rather than the
+//! address, so threads of processes which map the same memory, at any address, may use them
+//! together. They keep no other state, such as an owner or a registration, in process memory,
+//! and their layouts are `#[repr(C)]`, so they may be built in place within a mapping:
+//! `ptr::write` one made by `new` into the mapping before any process uses it.
+//!
+//! Whatever they guard must likewise be meaningful in every process, so hold no pointers nor
+//! handles.
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::AtomicU32;
+
+use time::Instant;
+
+/// Mutual exclusionary primitive, for memory shared between processes
+///
+/// As `Mutex`, but with shared futexes, which cost more than the private futexes of a `Mutex`,
+/// and with no tracking of its owner.
+#[repr(C)]
+#[derive(Debug)]
+pub struct IpcMutex<T: ?Sized> {
+    lock: ::system::IpcMutex,
+    valu: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for IpcMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for IpcMutex<T> {}
+
+impl<T> IpcMutex<T> {
+    #[inline] pub const fn new(x: T) -> Self {
+        IpcMutex { lock: ::system::IpcMutex::new(), valu: UnsafeCell::new(x) }
+    }
+}
+
+impl<T: ?Sized> IpcMutex<T> {
+    /// Take an exclusive reference to the guarded value, blocking if another thread, of any
+    /// process, is already holding it.
+    #[inline] pub fn lock(&self) -> IpcGuard<'_, T> {
+        self.lock.lock(::default_policy().spins);
+        IpcGuard { lock: &self.lock, valu: unsafe { &mut *self.valu.get() }, unsend: PhantomData }
+    }
+
+    /// Take an exclusive reference to the guarded value, returning `None` if another thread, of
+    /// any process, is already holding it.
+    #[inline] pub fn try_lock(&self) -> Option<IpcGuard<'_, T>> {
+        if self.lock.try_lock() {
+            Some(IpcGuard { lock: &self.lock, valu: unsafe { &mut *self.valu.get() },
+                            unsend: PhantomData })
+        } else { None }
+    }
+
+    /// Address of the futex word of the lock, e.g. to match to `futex` tracepoints
+    ///
+    /// This is only to identify the lock; writing through it would break the lock.
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { self.lock.futex_addr() }
+}
+
+/// Exclusive reference to `IpcMutex`-guarded value
+#[derive(Debug)]
+pub struct IpcGuard<'a, T: ?Sized + 'a> {
+    lock: &'a ::system::IpcMutex,
+    valu: &'a mut T,
+    unsend: PhantomData<*mut ()>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync> Sync for IpcGuard<'a, T> {}
+
+impl<'a, T: ?Sized> Deref for IpcGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { self.valu }
+}
+
+impl<'a, T: ?Sized> DerefMut for IpcGuard<'a, T> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { self.valu }
+}
+
+impl<'a, T: ?Sized> Drop for IpcGuard<'a, T> {
+    #[inline] fn drop(&mut self) { self.lock.unlock(::default_policy().spins) }
+}
+
+/// Condition variable, for memory shared between processes
+///
+/// As `CondVar`, but with shared futexes, for use with an `IpcMutex`. As it can't tell which
+/// mutex it is used with, using it with several is not caught, but is no less wrong.
+#[repr(C)]
+#[derive(Debug)]
+pub struct IpcCondVar(::system::IpcCondVar);
+
+impl Default for IpcCondVar {
+    #[inline] fn default() -> Self { IpcCondVar::new() }
+}
+
+impl IpcCondVar {
+    #[inline] pub const fn new() -> Self { IpcCondVar(::system::IpcCondVar::new()) }
+
+    /// Atomically release the guard lock and wait for another thread, of any process, to call
+    /// `notify`.
+    #[inline] pub fn wait<'a, T: ?Sized>(&self, guard: IpcGuard<'a, T>) -> IpcGuard<'a, T> {
+        self.0.wait(guard.lock);
+        guard
+    }
+
+    /// Address of the futex word on which waiters block, e.g. to match to `futex` tracepoints
+    ///
+    /// This is only to identify the condition variable; writing through it would break it.
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { self.0.futex_addr() }
+
+    /// Unblock one waiting thread, of any process.
+    #[inline] pub fn notify_one(&self) { self.0.notify_one() }
+
+    /// Unblock all waiting threads, of all processes.
+    #[inline] pub fn notify_all(&self) { self.0.notify_all() }
+}
+
+/// Barrier, for threads of processes sharing memory
+///
+/// As `Barrier`, but with shared futexes; `n` counts threads of all processes together.
+///
+/// To build one in place in a mapping, e.g. by the process which made it, before any other
+/// attaches:
+///
+/// ```
+/// # extern crate user_sync;
+/// # use std::ptr;
+/// use user_sync::IpcBarrier;
+///
+/// # fn main() {
+/// # let mut mapping = [0u64; 0x10];
+/// let p: *mut IpcBarrier = // ...in the mapping
+/// #   mapping.as_mut_ptr() as *mut _;
+/// unsafe { ptr::write(p, IpcBarrier::new(1)) };
+/// assert!(unsafe { &*p }.wait());
+/// # }
+/// ```
+#[repr(C)]
+#[derive(Debug)]
+pub struct IpcBarrier(::system::Barrier);
+
+impl IpcBarrier {
+    /// Make a barrier for `n` threads.
+    #[inline] pub const fn new(n: usize) -> Self { IpcBarrier(::system::Barrier::new_shared(n)) }
+
+    /// Wait until all threads reach the barrier.
+    /// Returns `true` in only one arbitrary thread and `false` in the rest.
+    #[inline] pub fn wait(&self) -> bool { self.0.wait() }
+
+    /// Wait until all threads reach the barrier, or until `deadline`; as `Barrier::wait_until`.
+    ///
+    /// The monotonic clock is the same in every process, so so is the deadline.
+    #[inline] pub fn wait_until(&self, deadline: Instant) -> Option<bool> {
+        self.0.wait_until(deadline.0)
+    }
+
+    /// Number of threads now waiting at the barrier
+    ///
+    /// Other threads may arrive or be released meanwhile, so this is only advisory.
+    #[inline] pub fn waiting(&self) -> usize { self.0.waiting() }
+
+    /// Address of the futex word on which waiters block, e.g. to match to `futex` tracepoints
+    ///
+    /// This is only to identify the barrier; writing through it would break it.
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { self.0.futex_addr() }
+
+    /// Current phase, which advances by one, modulo 2^32, each time all threads reach the barrier
+    #[inline] pub fn phase(&self) -> u32 { self.0.phase() }
+}
@@ -0,0 +1,65 @@
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+use wait_any::Waitable;
+
+/// Count which threads wait to reach zero, once
+///
+/// Counting down never blocks, so the threads which do so need not be the waiters, or even
+/// known to them; once the count reaches zero, it stays there, and `wait` returns at once.
+#[derive(Debug)]
+pub struct Latch(AtomicU32);
+
+impl Latch {
+    /// Make a latch which opens after `n` calls to `count_down`.
+    #[inline] pub const fn new(n: u32) -> Self { Latch(AtomicU32::new(n)) }
+
+    /// Count one down, unblocking all waiting threads if it reaches zero; return whether it
+    /// did so in this call. Once at zero, it stays there.
+    #[inline] pub fn count_down(&self) -> bool { self.count_down_by(1) }
+
+    /// Count `n` down, or to zero if less is left, unblocking all waiting threads if it reaches
+    /// zero; return whether it did so in this call.
+    ///
+    /// However many calls race to zero, only the one which takes the count there wakes the
+    /// waiters, so they are woken once.
+    #[inline] pub fn count_down_by(&self, n: u32) -> bool {
+        let mut m = self.0.load(Memord::Relaxed);
+        while 0 != m {
+            match self.0.compare_exchange_weak(m, m.saturating_sub(n),
+                                               Memord::Release, Memord::Relaxed) {
+                Ok(_) => {
+                    if n < m { return false };
+                    futex_wake(&self.0, !0);
+                    return true;
+                },
+                Err(k) => m = k,
+            }
+        }
+        false
+    }
+
+    /// Block until the count reaches zero.
+    #[inline] pub fn wait(&self) {
+        loop {
+            match self.0.load(Memord::Acquire) {
+                0 => return,
+                m => futex_wait(&self.0, m),
+            }
+        }
+    }
+
+    /// Whether the count reached zero, whereupon `wait` would not block
+    #[inline] pub fn is_open(&self) -> bool { 0 == self.0.load(Memord::Acquire) }
+
+    /// Count left
+    ///
+    /// Other threads may count down meanwhile, so this is only advisory.
+    #[inline] pub fn count(&self) -> u32 { self.0.load(Memord::Relaxed) }
+}
+
+impl Waitable for Latch {
+    #[inline] fn poll(&self) -> Result<(), (&AtomicU32, u32)> {
+        match self.0.load(Memord::Acquire) { 0 => Ok(()), m => Err((&self.0, m)) }
+    }
+}
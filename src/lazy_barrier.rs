@@ -0,0 +1,53 @@
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+
+/// Thread barrier whose number of threads is set once, after construction
+///
+/// Until `start` is called, `wait` blocks; once it is, threads already waiting are counted
+/// toward the first phase, and the barrier behaves as a `Barrier` for that many threads.
+#[derive(Debug)]
+pub struct LazyBarrier {
+    n_total: AtomicU32,
+    barrier: ::system::Barrier,
+}
+
+impl Default for LazyBarrier {
+    #[inline] fn default() -> Self { LazyBarrier::new() }
+}
+
+impl LazyBarrier {
+    #[inline] pub const fn new() -> Self {
+        LazyBarrier { n_total: AtomicU32::new(0), barrier: ::system::Barrier::new(0) }
+    }
+
+    /// Set the number of threads to `n`, unless already set; return whether this set it.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if `n` is 0.
+    #[inline] pub fn start(&self, n: u32) -> bool {
+        assert_ne!(0, n, "LazyBarrier started for no threads");
+        if self.n_total.compare_exchange(0, n, Memord::Release, Memord::Relaxed).is_err() {
+            return false;
+        }
+        futex_wake(&self.n_total, !0);
+        true
+    }
+
+    /// Number of threads, if set
+    #[inline] pub fn parties(&self) -> Option<u32> {
+        match self.n_total.load(Memord::Acquire) { 0 => None, n => Some(n) }
+    }
+
+    /// Wait until the number of threads is set, and then until that many reach the barrier.
+    /// Returns `true` in only one arbitrary thread and `false` in the rest.
+    #[inline] pub fn wait(&self) -> bool {
+        let mut n = self.n_total.load(Memord::Acquire);
+        while 0 == n {
+            futex_wait(&self.n_total, 0);
+            n = self.n_total.load(Memord::Acquire);
+        }
+        self.barrier.wait_n(n as usize)
+    }
+}
@@ -1,17 +1,122 @@
-#![feature(const_fn)]
-#![feature(integer_atomics)]
-
 #![no_std]
 
 #![deny(missing_debug_implementations)]
 
-#[macro_use] extern crate syscall;
+#[cfg(target_os = "linux")] #[macro_use] extern crate syscall;
+#[cfg(target_os = "redox")] extern crate redox_syscall;
+#[cfg(feature = "critical-section")] extern crate critical_section;
+#[cfg(feature = "lock_api")] extern crate lock_api;
+#[cfg(feature = "alloc")] extern crate alloc;
+#[cfg(feature = "std")] #[macro_use] extern crate std;
+
+#[cfg(all(feature = "critical-section", feature = "deadlock-detection"))]
+compile_error!("`deadlock-detection` is meaningless with `critical-section`");
+#[cfg(all(feature = "critical-section", feature = "stats"))]
+compile_error!("`stats` is not supported with `critical-section`");
+#[cfg(all(feature = "critical-section", feature = "backtrace"))]
+compile_error!("`backtrace` is not supported with `critical-section`");
+#[cfg(all(feature = "critical-section", feature = "named-locks"))]
+compile_error!("`named-locks` is not supported with `critical-section`");
+#[cfg(all(feature = "critical-section", feature = "robust"))]
+compile_error!("`robust` is not supported with `critical-section`");
+#[cfg(all(feature = "robust", not(target_os = "linux")))]
+compile_error!("`robust` is only supported on Linux");
+#[cfg(all(feature = "shared-futex", not(target_os = "linux")))]
+compile_error!("`shared-futex` is only supported on Linux");
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd", target_os = "redox",
+              feature = "critical-section")))]
+compile_error!("no backend for this system; `critical-section` may serve on a uniprocessor");
 
 #[cfg(target_os = "linux")] mod linux;
-#[cfg(target_os = "linux")] use ::linux as system;
+#[cfg(all(target_os = "windows", not(feature = "critical-section")))] mod windows;
+#[cfg(all(target_os = "macos", not(feature = "critical-section")))] mod macos;
+#[cfg(all(target_os = "openbsd", not(feature = "critical-section")))] mod openbsd;
+#[cfg(all(target_os = "netbsd", not(feature = "critical-section")))] mod netbsd;
+#[cfg(all(target_os = "redox", not(feature = "critical-section")))] mod redox;
+#[cfg(not(feature = "critical-section"))] mod system;
+
+#[cfg(feature = "critical-section")] mod interrupt;
+#[cfg(feature = "critical-section")] use ::interrupt as system;
 
-mod barrier;
+#[cfg(feature = "backtrace")] mod contention;
+#[cfg(feature = "deadlock-detection")] mod deadlock;
+#[cfg(feature = "named-locks")] mod registry;
+
+mod backoff;
+#[cfg(not(feature = "critical-section"))] mod barrier;
+#[cfg(not(feature = "critical-section"))] mod cancel;
+#[cfg(all(feature = "alloc", not(feature = "critical-section")))] mod channel;
+#[cfg(not(feature = "critical-section"))] mod combining;
+#[cfg(not(feature = "critical-section"))] mod event;
+#[cfg(not(feature = "critical-section"))] mod exchanger;
+#[cfg(not(feature = "critical-section"))] mod fair;
+#[cfg(not(feature = "critical-section"))] mod gate;
+#[cfg(all(target_os = "linux", not(feature = "critical-section")))] mod ipc;
+#[cfg(not(feature = "critical-section"))] mod latch;
+#[cfg(not(feature = "critical-section"))] mod lazy_barrier;
 mod monitor;
+#[cfg(not(feature = "critical-section"))] mod once;
+mod padded;
+#[cfg(not(feature = "critical-section"))] pub mod oneshot;
+#[cfg(all(feature = "std", not(feature = "critical-section")))] mod parallel;
+#[cfg(not(feature = "critical-section"))] mod phaser;
+#[cfg(all(feature = "alloc", not(feature = "critical-section")))] mod rcu;
+#[cfg(all(feature = "lock_api", not(feature = "critical-section")))] mod raw_lock;
+#[cfg(not(feature = "critical-section"))] mod reentrant;
+#[cfg(all(target_os = "linux", feature = "robust", not(feature = "critical-section")))]
+mod robust;
+#[cfg(not(feature = "critical-section"))] mod rwlock;
+#[cfg(not(feature = "critical-section"))] mod semaphore;
+mod seqlock;
+#[cfg(not(feature = "critical-section"))] pub mod spsc;
+mod system_sync;
+mod thread;
+#[cfg(not(feature = "critical-section"))] mod time;
+#[cfg(not(feature = "critical-section"))] mod wait_any;
+#[cfg(not(feature = "critical-section"))] mod wait_group;
+
+#[cfg(target_os = "linux")] pub mod futex;
+#[cfg(target_os = "linux")] pub mod raw;
+
+/// Number of times to try a contended lock before parking the thread
+///
+/// This is set when the crate is built, from the `STRAKE_SPINS` environment variable, or, if it
+/// is unset, `USER_SYNC_SPINS`, its former name, else 0x100; 0 is apt for uniprocessors, where
+/// the holder can't run while another thread spins.
+/// It is only the default: where a spin count is given for a lock, that is used instead, and
+/// `set_default_policy` may change it at run time.
+pub const DEFAULT_SPINS: usize = include!(concat!(env!("OUT_DIR"), "/spins"));
 
-pub use barrier::*;
+pub use backoff::*;
+#[cfg(not(feature = "critical-section"))] pub use barrier::*;
+#[cfg(feature = "backtrace")] pub use contention::{dump_hot_locks, set_contention_sample_period};
+#[cfg(not(feature = "critical-section"))] pub use cancel::*;
+#[cfg(all(feature = "alloc", not(feature = "critical-section")))] pub use channel::*;
+#[cfg(not(feature = "critical-section"))] pub use combining::*;
+#[cfg(not(feature = "critical-section"))] pub use event::*;
+#[cfg(not(feature = "critical-section"))] pub use exchanger::*;
+#[cfg(not(feature = "critical-section"))] pub use fair::*;
+#[cfg(not(feature = "critical-section"))] pub use gate::*;
+#[cfg(all(target_os = "linux", not(feature = "critical-section")))] pub use ipc::*;
+#[cfg(not(feature = "critical-section"))] pub use latch::*;
+#[cfg(not(feature = "critical-section"))] pub use lazy_barrier::*;
 pub use monitor::*;
+#[cfg(feature = "named-locks")] pub use registry::dump_locks;
+#[cfg(not(feature = "critical-section"))] pub use once::*;
+pub use padded::*;
+#[cfg(all(feature = "std", not(feature = "critical-section")))] pub use parallel::*;
+#[cfg(not(feature = "critical-section"))] pub use phaser::*;
+#[cfg(all(feature = "alloc", not(feature = "critical-section")))] pub use rcu::*;
+#[cfg(all(feature = "lock_api", not(feature = "critical-section")))] pub use raw_lock::*;
+#[cfg(not(feature = "critical-section"))] pub use reentrant::*;
+#[cfg(all(target_os = "linux", feature = "robust", not(feature = "critical-section")))]
+pub use robust::*;
+#[cfg(not(feature = "critical-section"))] pub use rwlock::*;
+#[cfg(not(feature = "critical-section"))] pub use semaphore::*;
+pub use seqlock::*;
+pub use system_sync::*;
+pub use thread::*;
+#[cfg(not(feature = "critical-section"))] pub use time::*;
+#[cfg(not(feature = "critical-section"))] pub use wait_any::*;
+#[cfg(not(feature = "critical-section"))] pub use wait_group::*;
@@ -1,148 +1,595 @@
-use core::ptr;
-use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering as Memord,
-                         spin_loop_hint as cpu_relax};
+//! Linux system calls, and the primitives only Linux supports
 
-// 0: unlocked
-// 1: locked
-// 2: locked and contended
+// With `critical-section`, only `futex` and `raw` use this.
+#![cfg_attr(feature = "critical-section", allow(dead_code))]
+
+#[cfg(feature = "std")] use core::cell::Cell;
+use core::hint::spin_loop as cpu_relax;
+#[cfg(feature = "robust")] use core::{fmt, mem, ptr};
+use core::time::Duration;
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use backoff::default_policy;
+use futex::{FutexError, check};
+
+// The kernel's robust list: at thread exit, for each node on the list of the thread, and the
+// node of a pending operation, if the word at `futex_offset` from it holds the thread's id, it
+// sets FUTEX_OWNER_DIED in the word, clears the id, and wakes a waiter. Its wake is shared, so
+// so must the lock's waits and wakes be.
+#[cfg(feature = "robust")]
+#[repr(C)]
+struct RobustList {
+    next: Cell<*const RobustList>,
+}
+
+#[cfg(feature = "robust")]
+#[repr(C)]
+struct RobustListHead {
+    list: RobustList,
+    futex_offset: Cell<isize>,
+    list_op_pending: Cell<*const RobustList>,
+}
+
+#[cfg(feature = "robust")]
+thread_local!(static ROBUST_HEAD: RobustListHead = const { RobustListHead {
+    list: RobustList { next: Cell::new(ptr::null()) },
+    futex_offset: Cell::new(0),
+    list_op_pending: Cell::new(ptr::null()),
+} });
+
+#[cfg(feature = "robust")]
+const FUTEX_WAITERS: u32 = 0x8000_0000;
+#[cfg(feature = "robust")]
+const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
+#[cfg(feature = "robust")]
+const FUTEX_TID_MASK: u32 = 0x3FFF_FFFF;
+
+// word: id of the owner, or'd with FUTEX_WAITERS if any thread may be waiting, and with
+// FUTEX_OWNER_DIED if the last owner died holding it. node is on the owner's robust list, which
+// links to it where it is, so it must not move while locked.
+#[cfg(feature = "robust")]
+#[repr(C)]
+#[derive(Debug)]
+pub struct RobustMutex {
+    node: RobustList,
+    word: AtomicU32,
+}
+
+#[cfg(feature = "robust")]
+unsafe impl Sync for RobustMutex {}
+
+#[cfg(feature = "robust")]
+impl fmt::Debug for RobustList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str("RobustList") }
+}
+
+#[cfg(feature = "robust")]
+impl RobustMutex {
+    #[inline] pub const fn new() -> Self {
+        RobustMutex { node: RobustList { next: Cell::new(ptr::null()) }, word: AtomicU32::new(0) }
+    }
+
+    // The calling thread's robust list, registered with the kernel if not yet; else why the
+    // kernel refused it, whereupon it would not release the thread's locks at its death. The
+    // list registered is this crate's own, in place of any of the C library.
+    #[inline] fn head(&self) -> Result<&RobustListHead, FutexError> {
+        let head = unsafe { &*robust_head() };
+        if head.list.next.get().is_null() {
+            watch_fork();
+            head.futex_offset.set(&self.word as *const _ as isize -
+                                  &self.node as *const _ as isize);
+            check(unsafe { syscall!(SET_ROBUST_LIST, head as *const RobustListHead,
+                                    mem::size_of::<RobustListHead>()) })?;
+            head.list.next.set(&head.list);
+        }
+        Ok(head)
+    }
+
+    /// Take the lock; return `false` if its last owner died holding it. It fails, without
+    /// taking the lock, if the kernel refuses the thread's robust list.
+    #[inline] pub fn lock(&self, spins: usize) -> Result<bool, FutexError> {
+        let head = self.head()?;
+        let tid = gettid();
+        head.list_op_pending.set(&self.node);
+        let mut waited = 0;
+        let mut k = 0;
+        let mut w = self.word.load(Memord::Relaxed);
+        loop {
+            if 0 == w & FUTEX_TID_MASK {
+                let new = tid | waited | w & FUTEX_WAITERS;
+                match self.word.compare_exchange(w, new, Memord::Acquire, Memord::Relaxed) {
+                    Ok(_) => break,
+                    Err(m) => w = m,
+                }
+            } else if k < spins {
+                k += 1;
+                cpu_relax();
+                w = self.word.load(Memord::Relaxed);
+            } else if 0 == w & FUTEX_WAITERS {
+                w = match self.word.compare_exchange(w, w | FUTEX_WAITERS, Memord::Relaxed,
+                                                     Memord::Relaxed) {
+                    Ok(_) => w | FUTEX_WAITERS,
+                    Err(m) => m,
+                };
+            } else {
+                futex_wait_shared(&self.word, w);
+                waited = FUTEX_WAITERS;
+                w = self.word.load(Memord::Relaxed);
+            }
+        }
+        self.enlist(head);
+        Ok(0 == w & FUTEX_OWNER_DIED)
+    }
+
+    /// Take the lock if free; return `None` if not, else whether its last owner lived. It
+    /// fails as `lock`.
+    #[inline] pub fn try_lock(&self) -> Result<Option<bool>, FutexError> {
+        let head = self.head()?;
+        head.list_op_pending.set(&self.node);
+        let w = self.word.load(Memord::Relaxed);
+        if 0 != w & FUTEX_TID_MASK ||
+           self.word.compare_exchange(w, gettid() | w & FUTEX_WAITERS, Memord::Acquire,
+                                      Memord::Relaxed).is_err() {
+            head.list_op_pending.set(ptr::null());
+            return Ok(None);
+        }
+        self.enlist(head);
+        Ok(Some(0 == w & FUTEX_OWNER_DIED))
+    }
+
+    #[inline] fn enlist(&self, head: &RobustListHead) {
+        self.node.next.set(head.list.next.get());
+        head.list.next.set(&self.node);
+        head.list_op_pending.set(ptr::null());
+    }
+
+    #[inline] pub fn unlock(&self) {
+        debug_assert_eq!(gettid(), self.word.load(Memord::Relaxed) & FUTEX_TID_MASK,
+                         "RobustMutex unlocked by a thread other than that which locked it");
+        let head = unsafe { &*robust_head() };
+        head.list_op_pending.set(&self.node);
+        // Unlink the node, if the list has it, as it may not in the child of a fork, whose list
+        // is another, by then.
+        let (node, end): (*const _, *const _) = (&self.node, &head.list);
+        let mut p = end;
+        loop {
+            let next = unsafe { (*p).next.get() };
+            if next == node { break unsafe { (*p).next.set(self.node.next.get()) } };
+            if next == end || next.is_null() { break };
+            p = next;
+        }
+        let w = self.word.swap(0, Memord::Release);
+        head.list_op_pending.set(ptr::null());
+        if 0 != w & FUTEX_WAITERS { futex_wake_shared(&self.word, 1); }
+    }
+
+    /// Thread which holds the lock, or 0 if none
+    #[inline] pub fn owner(&self) -> u32 { self.word.load(Memord::Relaxed) & FUTEX_TID_MASK }
+}
+
+#[cfg(feature = "robust")]
+impl Drop for RobustMutex {
+    fn drop(&mut self) {
+        match self.owner() {
+            0 => (),
+            tid if tid == gettid() => self.unlock(),
+            tid => panic!("RobustMutex dropped while held by thread {}, whose robust list has it",
+                          tid),
+        }
+    }
+}
+
+// The calling thread's robust list, by pointer, as it lives as long as the thread, which no
+// lifetime says.
+#[cfg(feature = "robust")]
+#[inline] fn robust_head() -> *const RobustListHead {
+    ROBUST_HEAD.with(|head| head as *const _)
+}
+
+// 0: `forked` not registered to run in the child of a fork
+// 1: being registered
+// 2: registered
+#[cfg(feature = "std")]
+static FORK_HOOK: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(feature = "std")]
+extern "C" {
+    fn pthread_atfork(prepare: Option<unsafe extern "C" fn()>,
+                      parent: Option<unsafe extern "C" fn()>,
+                      child: Option<unsafe extern "C" fn()>) -> i32;
+}
+
+// In the child of a fork, forget the thread id and robust list of the parent's thread: the
+// child's thread has another id, and the kernel holds no robust list for it, nor does it hold
+// the locks on the parent's.
+#[cfg(feature = "std")]
+unsafe extern "C" fn forked() {
+    TID.with(|tid| tid.set(0));
+    #[cfg(feature = "robust")] ROBUST_HEAD.with(|head| {
+        head.list.next.set(ptr::null());
+        head.list_op_pending.set(ptr::null());
+    });
+}
+
+// Have `forked` run in the child of every fork from now on, before the calling thread keeps
+// any state which it must reset.
+#[cfg(feature = "std")]
+#[inline] fn watch_fork() {
+    if 2 == FORK_HOOK.load(Memord::Acquire) { return };
+    if FORK_HOOK.compare_exchange(0, 1, Memord::Acquire, Memord::Acquire).is_ok() {
+        unsafe { pthread_atfork(None, None, Some(forked)) };
+        FORK_HOOK.store(2, Memord::Release);
+    }
+    while 2 != FORK_HOOK.load(Memord::Acquire) { cpu_relax() }
+}
+
+// As Mutex, but its waits and wakes are shared, and it keeps no state but the word, which has
+// no addresses nor thread ids, so it works wherever it is mapped.
+#[repr(C)]
 #[derive(Debug)]
-pub struct Mutex(AtomicU32);
+pub struct IpcMutex {
+    word: AtomicU32,
+}
 
-impl Mutex {
-    #[inline] pub const fn new() -> Self { Mutex(AtomicU32::new(0)) }
+impl IpcMutex {
+    #[inline] pub const fn new() -> Self { IpcMutex { word: AtomicU32::new(0) } }
 
     #[inline] pub fn lock(&self, spins: usize) {
-        let &Mutex(ref m) = self;
-        let mut n = 0;
         for _ in 0..spins {
-            n = m.compare_and_swap(0, 1, Memord::Acquire);
-            if n == 0 { return };
+            if self.word.compare_exchange(0, 1, Memord::Acquire, Memord::Relaxed).is_ok() {
+                return;
+            }
             cpu_relax();
         }
-        if n == 1 { n = m.swap(2, Memord::AcqRel) };
-        while n > 0 {
-            futex_wait(&m, 2);
-            n = m.swap(2, Memord::Release);
-        }
+        self.lock_contended();
+    }
+
+    #[inline] fn lock_contended(&self) {
+        while self.word.swap(2, Memord::AcqRel) > 0 { futex_wait_shared(&self.word, 2) }
     }
 
     #[inline] pub fn unlock(&self, spins: usize) {
-        let &Mutex(ref m) = self;
+        let m = &self.word;
+        debug_assert_ne!(0, m.load(Memord::Relaxed),
+                         "IpcMutex unlocked while not locked; was a guard dropped twice?");
         if m.swap(0, Memord::Release) == 1 { return };
         for _ in 0..spins {
             if m.load(Memord::Acquire) > 0 &&
-               m.compare_and_swap(1, 2, Memord::AcqRel) > 0 { return };
+               m.compare_exchange(1, 2, Memord::AcqRel, Memord::Acquire)
+                .unwrap_or_else(|n| n) > 0 {
+                return;
+            }
             cpu_relax();
         }
-        futex_wake(&m, 1);
+        futex_wake_shared(m, 1);
     }
 
     #[inline] pub fn try_lock(&self) -> bool {
-        self.0.compare_and_swap(0, 1, Memord::Acquire) == 0
+        self.word.compare_exchange(0, 1, Memord::Acquire, Memord::Relaxed).is_ok()
     }
+
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { &self.word }
 }
 
+// As CondVar, with shared waits and wakes, and no record of the mutex, whose address may differ
+// between processes.
+#[repr(C)]
 #[derive(Debug)]
-pub struct Barrier {
-    n_waiting: AtomicUsize,
-    n_total: usize,
+pub struct IpcCondVar {
     seq: AtomicU32,
 }
 
-impl Barrier {
-    #[inline] pub const fn new(n: usize) -> Self {
-        Barrier {
-            n_waiting: AtomicUsize::new(0),
-            n_total: n,
-            seq: AtomicU32::new(0 /* mem::uninitialized */),
-        }
+impl IpcCondVar {
+    #[inline] pub const fn new() -> Self { IpcCondVar { seq: AtomicU32::new(0) } }
+
+    #[inline] pub fn wait(&self, m: &IpcMutex) {
+        debug_assert_ne!(0, m.word.load(Memord::Relaxed));
+        let seq = self.seq.load(Memord::Acquire);
+        m.unlock(default_policy().spins);
+        futex_wait_shared(&self.seq, seq);
+        m.lock_contended();
     }
 
-    #[inline] pub fn wait(&self) -> bool {
-        loop {
-            let seq = self.seq.load(Memord::Acquire);
-            let n_waiting = self.n_waiting.fetch_add(1, Memord::Relaxed) + 1;
-            if n_waiting < self.n_total {
-                while self.seq.load(Memord::Relaxed) == seq {
-                    futex_wait(&self.seq, seq);
-                }
-                return false;
-            }
-            if n_waiting == self.n_total {
-                self.n_waiting.store(0, Memord::Relaxed);
-                self.seq.fetch_add(1, Memord::Release);
-                futex_wake(&self.seq, !0);
-                return true;
-            }
-            panic!("Too many waiters");
-        }
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { &self.seq }
+
+    #[inline] pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Memord::SeqCst);
+        futex_wake_shared(&self.seq, 1);
+    }
+
+    #[inline] pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Memord::SeqCst);
+        futex_wake_shared(&self.seq, !0);
     }
 }
 
-#[derive(Debug)]
-pub struct CondVar {
-    ptr: AtomicPtr<AtomicU32>,
-    seq: AtomicU32,
+// Identifier of the calling thread, once it is known, with `std`, whereby a fork resets it
+#[cfg(feature = "std")]
+thread_local!(static TID: Cell<u32> = const { Cell::new(0) });
+
+/// Identifier of the calling thread
+#[cfg(feature = "std")]
+#[inline]
+pub fn gettid() -> u32 { TID.with(|tid| {
+    if 0 == tid.get() {
+        watch_fork();
+        tid.set(unsafe { syscall!(GETTID) } as u32);
+    }
+    tid.get()
+}) }
+
+/// Identifier of the calling thread
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn gettid() -> u32 { unsafe { syscall!(GETTID) as u32 } }
+
+/// Let another thread run.
+#[inline]
+pub fn yield_now() { unsafe {
+    syscall!(SCHED_YIELD);
+} }
+
+pub(crate) const EAGAIN: usize = 11;
+pub(crate) const EINTR: usize = 4;
+pub(crate) const EINVAL: usize = 22;
+pub(crate) const ENOSYS: usize = 38;
+pub(crate) const ETIMEDOUT: usize = 110;
+
+#[repr(C)]
+pub(crate) struct Timespec {
+    tv_sec: isize,
+    tv_nsec: isize,
 }
 
-impl CondVar {
-    #[inline] pub const fn new() -> Self {
-        CondVar {
-            ptr: AtomicPtr::new(ptr::null_mut()),
-            seq: AtomicU32::new(0 /* mem::uninitialized */),
+impl Timespec {
+    // A deadline beyond what `tv_sec` holds would be taken as negative, and fail the wait, so
+    // clamp it; it is the same as never.
+    #[inline] pub(crate) fn from_nanos(t: u64) -> Self {
+        let secs = t / 1_000_000_000;
+        Timespec {
+            tv_sec: if secs > isize::MAX as u64 { isize::MAX }
+                    else { secs as isize },
+            tv_nsec: (t % 1_000_000_000) as isize,
         }
     }
+}
 
-    #[inline] pub fn wait(&self, m: &Mutex) {
-        debug_assert_ne!(0, m.0.load(Memord::Relaxed));
-
-        let seq = self.seq.load(Memord::Acquire);
-        let ptr = self.ptr.load(Memord::Acquire);
-        if ptr.is_null() {
-            self.ptr.compare_and_swap(ptr::null_mut(), ptr, Memord::AcqRel);
-            debug_assert_eq!(ptr, self.ptr.load(Memord::Relaxed),
-                             "CondVar used with multiple Mutexen");
-        } else {
-            debug_assert_eq!(ptr as *const _, &m.0, "CondVar used with multiple Mutexen");
+impl From<Duration> for Timespec {
+    #[inline] fn from(d: Duration) -> Self {
+        Timespec {
+            tv_sec: if d.as_secs() > isize::MAX as u64 { isize::MAX }
+                    else { d.as_secs() as isize },
+            tv_nsec: d.subsec_nanos() as isize,
         }
+    }
+}
 
-        m.unlock(0x100);
+pub(crate) const CLOCK_MONOTONIC: usize = 1;
 
-        futex_wait(&self.seq, seq);
+/// Nanoseconds on the monotonic clock
+#[inline]
+pub fn monotonic_now() -> u64 {
+    let mut ts = Timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { syscall!(CLOCK_GETTIME, CLOCK_MONOTONIC, &mut ts as *mut _) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
 
-        while 0 != m.0.swap(2, Memord::AcqRel) {
-            futex_wait(&m.0, 2);
-        }
+pub(crate) const FUTEX_WAIT: usize = 0;
+pub(crate) const FUTEX_WAKE: usize = 1;
+pub(crate) const FUTEX_REQUEUE: usize = 3;
+pub(crate) const FUTEX_CMP_REQUEUE: usize = 4;
+pub(crate) const FUTEX_WAKE_OP: usize = 5;
+pub(crate) const FUTEX_WAIT_BITSET: usize = 9;
+pub(crate) const FUTEX_WAKE_BITSET: usize = 10;
+pub(crate) const FUTEX_BITSET_MATCH_ANY: u32 = !0;
+// Private futexes are keyed by address within the process; shared ones by the backing page, so
+// they work across processes which map the word, but each wait and wake must look up the
+// mapping, and take a reference to the page, which costs more.
+#[cfg(not(feature = "shared-futex"))] pub(crate) const FUTEX_PRIVATE_FLAG: usize = 0x80;
+#[cfg(feature = "shared-futex")] pub(crate) const FUTEX_PRIVATE_FLAG: usize = 0;
+
+/// Block while `*f == val`.
+///
+/// The comparison and the sleep are atomic with respect to `futex_wake` on the same word.
+/// May return spuriously, so the caller must recheck its condition.
+#[inline]
+pub fn futex_wait(f: &AtomicU32, val: u32) { wait_flags(f, val, FUTEX_PRIVATE_FLAG) }
+
+/// Block while `*f == val`, for at most `timeout`; return `false` if it timed out.
+///
+/// As `futex_wait`, may return spuriously. It fails only if the kernel refuses the wait, e.g.
+/// as a sandbox forbids the call.
+#[inline]
+pub fn futex_wait_timeout(f: &AtomicU32, val: u32,
+                          timeout: Duration) -> Result<bool, FutexError> {
+    futex_wait_until(f, val, monotonic_now().saturating_add(nanos(timeout)))
+}
+
+/// As `futex_wait`, but return `false` if a signal handler ran meanwhile.
+///
+/// Only handlers installed without `SA_RESTART` interrupt the wait; else the kernel restarts it.
+#[inline]
+pub fn futex_wait_interruptible(f: &AtomicU32, val: u32) -> bool {
+    let r = unsafe { syscall!(FUTEX, f as *const _, FUTEX_WAIT | FUTEX_PRIVATE_FLAG, val, 0) };
+    match check(r) {
+        Err(FutexError::Interrupted) => false,
+        r => {
+            debug_assert!(r.is_ok() || r == Err(FutexError::Again), "futex wait failed: {:?}", r);
+            true
+        },
     }
+}
 
-    #[inline] pub fn notify_one(&self) {
-        self.seq.fetch_add(1, Memord::Relaxed);
-        futex_wake(&self.seq, 1);
+/// Block while `*f == val`, until `deadline`, in nanoseconds on the monotonic clock.
+///
+/// Returns `Ok(false)` if the deadline passed, else fails as `futex_wait_timeout`, or returns
+/// as `futex_wait`.
+#[inline]
+pub fn futex_wait_until(f: &AtomicU32, val: u32, deadline: u64) -> Result<bool, FutexError> {
+    wait_until_flags(f, val, deadline, FUTEX_PRIVATE_FLAG)
+}
+
+// The kernel takes the count as a signed int, and wakes at most one thread if it is negative,
+// so clamp it, lest `!0` mean 1 rather than all.
+#[inline] pub(crate) fn wake_count(n: usize) -> usize { n.min(i32::MAX as usize) }
+
+#[inline] fn nanos(d: Duration) -> u64 {
+    d.as_secs().saturating_mul(1_000_000_000).saturating_add(d.subsec_nanos() as u64)
+}
+
+// A signal handler installed without `SA_RESTART` interrupts a wait, which would then return
+// as if woken, so restart it: the deadline is absolute, so a restarted timed wait ends when the
+// first would have. Given a word the caller may reference, an untimed wait can't fail else but
+// where the kernel refuses the call, whereupon the caller would spin.
+#[inline] fn wait_flags(f: &AtomicU32, val: u32, flags: usize) {
+    loop {
+        match check(unsafe { syscall!(FUTEX, f as *const _, FUTEX_WAIT | flags, val, 0) }) {
+            Err(FutexError::Interrupted) => (),
+            r => {
+                debug_assert!(r.is_ok() || r == Err(FutexError::Again),
+                              "futex wait failed: {:?}", r);
+                return;
+            },
+        }
     }
+}
 
-    #[inline] pub fn notify_all(&self) {
-        let ptr = self.ptr.load(Memord::Acquire);
-        if ptr.is_null() { return; }
-        self.seq.fetch_add(1, Memord::Relaxed);
-        futex_reque(&self.seq, 1, !0, ptr);
+#[inline]
+fn wait_until_flags(f: &AtomicU32, val: u32, deadline: u64,
+                    flags: usize) -> Result<bool, FutexError> {
+    let ts = Timespec::from_nanos(deadline);
+    loop {
+        match check(unsafe {
+            syscall!(FUTEX, f as *const _, FUTEX_WAIT_BITSET | flags, val, &ts as *const _, 0,
+                     FUTEX_BITSET_MATCH_ANY)
+        }) {
+            Err(FutexError::Interrupted) => (),
+            Err(FutexError::TimedOut) => return Ok(false),
+            Ok(_) | Err(FutexError::Again) => return Ok(true),
+            Err(e) => return Err(e),
+        }
     }
 }
 
-const FUTEX_WAIT: usize = 0;
-const FUTEX_WAKE: usize = 1;
-const FUTEX_PRIVATE_FLAG: usize = 0x80;
+// A timed wait of the crate's own primitives which failed would fail again if retried, and is
+// no timeout, so panic.
+#[cold]
+pub(crate) fn wait_failed(e: FutexError) -> ! { panic!("futex wait failed: {}", e) }
 
 #[inline]
-pub fn futex_wait(f: &AtomicU32, val: u32) { unsafe {
-    syscall!(FUTEX, f as *const _, FUTEX_WAIT | FUTEX_PRIVATE_FLAG, val, 0);
-} }
+pub(crate) fn futex_wait_shared(f: &AtomicU32, val: u32) { wait_flags(f, val, 0) }
 
 #[inline]
-pub fn futex_wake(f: &AtomicU32, n: usize) -> usize { unsafe {
-    syscall!(FUTEX, f as *const _, FUTEX_WAKE | FUTEX_PRIVATE_FLAG, n)
-} }
+pub(crate) fn futex_wait_until_shared(f: &AtomicU32, val: u32,
+                                      deadline: u64) -> Result<bool, FutexError> {
+    wait_until_flags(f, val, deadline, 0)
+}
 
 #[inline]
-pub fn futex_reque(f: &AtomicU32, n: usize, m: usize, ptr: *mut AtomicU32) -> usize { unsafe {
-    syscall!(FUTEX, f as *const _, n, m, ptr)
-} }
+pub(crate) fn futex_wake_shared(f: &AtomicU32, n: usize) -> usize {
+    check(unsafe { syscall!(FUTEX, f as *const _, FUTEX_WAKE, wake_count(n)) }).unwrap_or(0)
+}
+
+/// Unblock at most `n` threads waiting on `f`; return how many were unblocked, 0 if the kernel
+/// refused the call.
+#[inline]
+pub fn futex_wake(f: &AtomicU32, n: usize) -> usize {
+    check(unsafe {
+        syscall!(FUTEX, f as *const _, FUTEX_WAKE | FUTEX_PRIVATE_FLAG, wake_count(n))
+    }).unwrap_or(0)
+}
+
+/// Atomically modify `f2` by `op`, unblock at most `n1` threads waiting on `f1`, and, if the
+/// old value of `f2` satisfies the comparison of `op`, unblock at most `n2` threads waiting on
+/// `f2`; return how many threads were unblocked in total.
+///
+/// `op` is made by `futex_op`.
+#[inline]
+pub fn futex_wake_op(f1: &AtomicU32, n1: usize, f2: &AtomicU32, n2: usize, op: u32) -> usize {
+    check(unsafe {
+        syscall!(FUTEX, f1 as *const _, FUTEX_WAKE_OP | FUTEX_PRIVATE_FLAG, wake_count(n1),
+                 wake_count(n2), f2 as *const _, op)
+    }).unwrap_or(0)
+}
+
+// Store `val`, at most 12 bits, in `f`, and unblock a thread waiting on it; the kernel does
+// both, so the caller never touches `f` after the store.
+#[inline]
+pub(crate) fn futex_store_wake(f: &AtomicU32, val: u32) {
+    futex_wake_op(f, 1, f, 0, futex_op(FUTEX_OP_SET, val, FUTEX_OP_CMP_EQ, 0));
+}
+
+// Unblock a thread waiting on each of `f1` and `f2`, in one system call; return how many were
+// unblocked in all.
+#[inline]
+pub(crate) fn futex_wake_both(f1: &AtomicU32, f2: &AtomicU32) -> usize {
+    futex_wake_op(f1, 1, f2, 1, WAKE_ALWAYS)
+}
+
+// Operation for `futex_wake_op` which leaves the second word as it is, and wakes its waiters
+// whatever it holds
+const WAKE_ALWAYS: u32 = futex_op(FUTEX_OP_OR, 0, FUTEX_OP_CMP_GE, 0);
+
+/// If `*f1 == val`, unblock at most `n_wake` threads waiting on `f1`, and move at most
+/// `n_requeue` more to wait on `f2` instead; return how many threads were unblocked and moved in
+/// total, or `None` if `*f1 != val`.
+///
+/// A moved thread stays blocked until woken on `f2`, whereupon it returns from its wait on `f1`.
+#[inline]
+pub fn futex_cmp_requeue(f1: &AtomicU32, val: u32, n_wake: usize,
+                         f2: &AtomicU32, n_requeue: usize) -> Option<usize> {
+    cmp_requeue(f1, val, n_wake, f2, n_requeue)
+}
+
+// As `futex_cmp_requeue`, but `f2` may dangle, as the kernel only keys waiters by it. Should
+// the kernel refuse the requeue otherwise than for `*f1 != val`, wake the threads instead, which
+// is as good but for waking them early, lest a caller retry forever or lose the notification.
+#[inline]
+pub(crate) fn cmp_requeue(f1: &AtomicU32, val: u32, n_wake: usize,
+               f2: *const AtomicU32, n_requeue: usize) -> Option<usize> {
+    let r = unsafe {
+        syscall!(FUTEX, f1 as *const _, FUTEX_CMP_REQUEUE | FUTEX_PRIVATE_FLAG,
+                 wake_count(n_wake), wake_count(n_requeue), f2, val)
+    };
+    match check(r) {
+        Ok(n) => Some(n),
+        Err(FutexError::Again) => None,
+        Err(_) => Some(futex_wake(f1, n_wake.saturating_add(n_requeue))),
+    }
+}
+
+/// `*f2 = oparg`
+pub const FUTEX_OP_SET: u32 = 0;
+/// `*f2 += oparg`
+pub const FUTEX_OP_ADD: u32 = 1;
+/// `*f2 |= oparg`
+pub const FUTEX_OP_OR: u32 = 2;
+/// `*f2 &= !oparg`
+pub const FUTEX_OP_ANDN: u32 = 3;
+/// `*f2 ^= oparg`
+pub const FUTEX_OP_XOR: u32 = 4;
+/// Flag to `futex_op` operation to use `1 << oparg` rather than `oparg`
+pub const FUTEX_OP_OPARG_SHIFT: u32 = 8;
+
+/// Wake if `old == cmparg`
+pub const FUTEX_OP_CMP_EQ: u32 = 0;
+/// Wake if `old != cmparg`
+pub const FUTEX_OP_CMP_NE: u32 = 1;
+/// Wake if `old < cmparg`
+pub const FUTEX_OP_CMP_LT: u32 = 2;
+/// Wake if `old <= cmparg`
+pub const FUTEX_OP_CMP_LE: u32 = 3;
+/// Wake if `old > cmparg`
+pub const FUTEX_OP_CMP_GT: u32 = 4;
+/// Wake if `old >= cmparg`
+pub const FUTEX_OP_CMP_GE: u32 = 5;
+
+/// Encode an operation for `futex_wake_op`.
+///
+/// `op` is one of the `FUTEX_OP_*` operations, optionally or'd with `FUTEX_OP_OPARG_SHIFT`;
+/// `cmp` is one of the `FUTEX_OP_CMP_*` comparisons.
+/// `oparg` and `cmparg` are truncated to 12 bits.
+#[inline]
+pub const fn futex_op(op: u32, oparg: u32, cmp: u32, cmparg: u32) -> u32 {
+    (op & 0xf) << 28 | (cmp & 0xf) << 24 | (oparg & 0xfff) << 12 | cmparg & 0xfff
+}
@@ -0,0 +1,164 @@
+//! macOS system calls
+//!
+//! `__ulock_wait` blocks while a word holds a value, and `__ulock_wake` wakes threads so blocked,
+//! as futexes do, so the futex backend runs on them as it is. These are the calls beneath
+//! `os_unfair_lock` and the C++ library's atomic waits, though Apple doesn't document them. A
+//! wake tells only whether there was a thread to wake, so a wake of more than one wakes all, as
+//! waking them one at a time until none is left might never end while threads keep blocking;
+//! they can't move waiters between words.
+
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+use core::time::Duration;
+
+#[link(name = "System")]
+extern "C" {
+    fn __ulock_wait(operation: u32, addr: *mut c_void, value: u64, micros: u32) -> i32;
+    fn __ulock_wake(operation: u32, addr: *mut c_void, wake_value: u64) -> i32;
+    fn pthread_self() -> usize;
+    fn pthread_mach_thread_np(thread: usize) -> u32;
+    fn sched_yield() -> i32;
+    fn clock_gettime_nsec_np(clock: u32) -> u64;
+}
+
+const UL_COMPARE_AND_WAIT: u32 = 1;
+const UL_COMPARE_AND_WAIT_SHARED: u32 = 3;
+// Wake all waiters rather than one
+const ULF_WAKE_ALL: u32 = 0x100;
+// Return the error negated rather than set `errno`
+const ULF_NO_ERRNO: u32 = 0x0100_0000;
+
+const EINTR: i32 = 4;
+const EFAULT: i32 = 14;
+const ETIMEDOUT: i32 = 60;
+
+// Monotonic clock of `Instant` in the standard library, which doesn't count while asleep
+const CLOCK_UPTIME_RAW: u32 = 8;
+
+#[inline] fn addr(f: &AtomicU32) -> *mut c_void { f as *const _ as *mut c_void }
+
+// Block while `*f == val`, for at most `micros`, or until woken if 0; return `false` if it timed
+// out. A signal or a fault on the page of `f` makes a spurious return, as on Linux.
+#[inline] fn wait(op: u32, f: &AtomicU32, val: u32, micros: u32) -> Result<bool, i32> {
+    match unsafe { __ulock_wait(op | ULF_NO_ERRNO, addr(f), val as u64, micros) } {
+        r if r >= 0 => Ok(true),
+        r if -r == ETIMEDOUT => Ok(false),
+        r if -r == EINTR || -r == EFAULT => Ok(true),
+        r => Err(-r),
+    }
+}
+
+#[inline] fn wait_until(op: u32, f: &AtomicU32, val: u32, deadline: u64) -> Result<bool, i32> {
+    loop {
+        let now = monotonic_now();
+        if now >= deadline { return Ok(false) };
+        let micros = ((deadline - now - 1) / 1_000 + 1).min(!0u32 as u64) as u32;
+        if wait(op, f, val, micros)? { return Ok(true) };
+    }
+}
+
+// Unblock one thread waiting on `f`, or all if `n > 1`; return 1 if there was any, else 0.
+#[inline] fn wake(op: u32, f: &AtomicU32, n: usize) -> usize {
+    if 0 == n { return 0 };
+    let op = if n > 1 { op | ULF_WAKE_ALL } else { op };
+    (0 == unsafe { __ulock_wake(op | ULF_NO_ERRNO, addr(f), 0) }) as usize
+}
+
+/// Identifier of the calling thread
+#[inline]
+pub fn gettid() -> u32 { unsafe { pthread_mach_thread_np(pthread_self()) } }
+
+/// Let another thread run.
+#[inline]
+pub fn yield_now() { unsafe { sched_yield(); } }
+
+/// Nanoseconds on the monotonic clock
+#[inline]
+pub fn monotonic_now() -> u64 { unsafe { clock_gettime_nsec_np(CLOCK_UPTIME_RAW) } }
+
+/// Block while `*f == val`.
+///
+/// As on Linux, may return spuriously, so the caller must recheck its condition.
+#[inline]
+pub fn futex_wait(f: &AtomicU32, val: u32) {
+    let r = wait(UL_COMPARE_AND_WAIT, f, val, 0);
+    debug_assert!(r.is_ok(), "__ulock_wait failed: error {:?}", r);
+}
+
+/// Block while `*f == val`, for at most `timeout`; return `false` if it timed out.
+#[inline]
+pub fn futex_wait_timeout(f: &AtomicU32, val: u32, timeout: Duration) -> Result<bool, i32> {
+    let nanos = timeout.as_secs().saturating_mul(1_000_000_000)
+                       .saturating_add(timeout.subsec_nanos() as u64);
+    futex_wait_until(f, val, monotonic_now().saturating_add(nanos))
+}
+
+/// As `futex_wait`, but return `false` if a signal interrupted it.
+#[inline]
+pub fn futex_wait_interruptible(f: &AtomicU32, val: u32) -> bool {
+    match unsafe { __ulock_wait(UL_COMPARE_AND_WAIT | ULF_NO_ERRNO, addr(f), val as u64, 0) } {
+        r if -r == EINTR => false,
+        r => { debug_assert!(r >= 0 || -r == EFAULT, "__ulock_wait failed: error {}", -r); true },
+    }
+}
+
+/// Block while `*f == val`, until `deadline`, in nanoseconds on the monotonic clock.
+///
+/// Returns `Ok(false)` if the deadline passed, else as `futex_wait`. The wait is timed in whole
+/// microseconds, rounded up, so it may overrun the deadline by as much, but never ends early.
+#[inline]
+pub fn futex_wait_until(f: &AtomicU32, val: u32, deadline: u64) -> Result<bool, i32> {
+    wait_until(UL_COMPARE_AND_WAIT, f, val, deadline)
+}
+
+// A timed wait of the crate's own primitives which failed would fail again if retried, and is
+// no timeout, so panic.
+#[cold]
+pub(crate) fn wait_failed(e: i32) -> ! { panic!("__ulock_wait failed: error {}", e) }
+
+#[inline]
+pub(crate) fn futex_wait_shared(f: &AtomicU32, val: u32) {
+    let r = wait(UL_COMPARE_AND_WAIT_SHARED, f, val, 0);
+    debug_assert!(r.is_ok(), "__ulock_wait failed: error {:?}", r);
+}
+
+#[inline]
+pub(crate) fn futex_wait_until_shared(f: &AtomicU32, val: u32,
+                                      deadline: u64) -> Result<bool, i32> {
+    wait_until(UL_COMPARE_AND_WAIT_SHARED, f, val, deadline)
+}
+
+#[inline]
+pub(crate) fn futex_wake_shared(f: &AtomicU32, n: usize) -> usize {
+    wake(UL_COMPARE_AND_WAIT_SHARED, f, n)
+}
+
+/// Unblock at most `n` threads waiting on `f`; return how many were unblocked.
+///
+/// The call wakes one thread or all, so if `n > 1` this wakes all, and returns 1 if it woke any,
+/// as it can't tell how many.
+#[inline]
+pub fn futex_wake(f: &AtomicU32, n: usize) -> usize { wake(UL_COMPARE_AND_WAIT, f, n) }
+
+// Store `val` in `f`, and unblock a thread waiting on it; the wake only keys the waiter by the
+// address of `f`, so it needn't be valid by then.
+#[inline]
+pub(crate) fn futex_store_wake(f: &AtomicU32, val: u32) {
+    let p = addr(f);
+    f.store(val, Memord::Release);
+    unsafe { __ulock_wake(UL_COMPARE_AND_WAIT | ULF_NO_ERRNO, p, 0) };
+}
+
+// Unblock a thread waiting on each of `f1` and `f2`; return how many were unblocked.
+#[inline]
+pub(crate) fn futex_wake_both(f1: &AtomicU32, f2: &AtomicU32) -> usize {
+    futex_wake(f1, 1) + futex_wake(f2, 1)
+}
+
+// Lacking a requeue, unblock the threads rather than move them; they then block on `f2` as they
+// take it, if they must. As none is moved, it needn't compare `*f1` to `val`.
+#[inline]
+pub(crate) fn cmp_requeue(f1: &AtomicU32, _val: u32, n_wake: usize,
+                          _f2: *const AtomicU32, n_requeue: usize) -> Option<usize> {
+    Some(futex_wake(f1, n_wake.saturating_add(n_requeue)))
+}
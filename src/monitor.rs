@@ -1,13 +1,56 @@
+#[cfg(feature = "alloc")] use alloc::sync::Arc;
 use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem;
 use core::ops::{Deref, DerefMut};
+#[cfg(not(feature = "critical-section"))] use core::sync::atomic::AtomicU32;
+#[cfg(not(feature = "critical-section"))] use core::time::Duration;
+
+#[cfg(not(feature = "critical-section"))] use cancel::CancelToken;
+use backoff::Backoff;
+use system_sync::{DefaultSystem, SystemSync};
+#[cfg(not(feature = "critical-section"))] use system_sync::SystemCondVar;
+use thread::ThreadId;
+#[cfg(not(feature = "critical-section"))] use time::Instant;
 
 /// Mutual exclusionary primitive
+///
+/// It is generic over the system lock beneath, by default this crate's own; see `SystemSync`.
+/// Most methods beyond `lock` and `try_lock` are for the default only.
 #[derive(Debug)]
-pub struct Mutex<T: ?Sized> {
-    lock: ::system::Mutex,
+pub struct Mutex<T: ?Sized, S: SystemSync = DefaultSystem> {
+    lock: S::Mutex,
     valu: UnsafeCell<T>,
 }
 
+// SAFETY: the value is reached only through a guard, and the lock lets but one thread hold a
+// guard at a time, so sharing the mutex no more shares the value than sending it between the
+// threads would; hence `T: Send` suffices, as for `std::sync::Mutex`.
+unsafe impl<T: ?Sized + Send, S: SystemSync> Sync for Mutex<T, S> {}
+
+/// Array of `n` unlocked `Mutex<()>`, e.g. a lock table in a `static`
+///
+/// `n` must be a constant expression; the array is built at compile time.
+///
+/// ```
+/// # #[macro_use] extern crate user_sync;
+/// use user_sync::Mutex;
+///
+/// static LOCKS: [Mutex<()>; 0x10] = mutex_array!(0x10);
+///
+/// # fn main() {
+/// let key = 0x2A_usize;
+/// let _guard = LOCKS[key % LOCKS.len()].lock();
+/// # }
+/// ```
+#[macro_export]
+macro_rules! mutex_array {
+    ($n:expr) => ({
+        const UNLOCKED: $crate::Mutex<()> = $crate::Mutex::new(());
+        [UNLOCKED; $n]
+    })
+}
+
 impl<T> Mutex<T> {
     #[inline] pub const fn new(x: T) -> Self {
         Mutex {
@@ -15,62 +58,450 @@ impl<T> Mutex<T> {
             valu: UnsafeCell::new(x),
         }
     }
+
+    /// Make a mutex which `register` lists in `dump_locks` under `name`.
+    #[cfg(feature = "named-locks")]
+    #[inline] pub const fn new_named(x: T, name: &'static str) -> Self {
+        Mutex {
+            lock: ::system::Mutex::new_named(name),
+            valu: UnsafeCell::new(x),
+        }
+    }
+
+    /// Make a mutex which spins by `backoff` while contended; `new` spins by `Backoff::Pause`.
+    #[inline] pub const fn with_backoff(x: T, backoff: Backoff) -> Self {
+        Mutex {
+            lock: ::system::Mutex::with_backoff(backoff),
+            valu: UnsafeCell::new(x),
+        }
+    }
 }
 
-impl<T: ?Sized> Mutex<T> {
+impl<T, S: SystemSync> Mutex<T, S> {
+    /// Make a mutex over the system lock of `S`; `new` is for `DefaultSystem`.
+    #[inline] pub const fn with_system(x: T) -> Self {
+        Mutex { lock: S::MUTEX_INIT, valu: UnsafeCell::new(x) }
+    }
+
+    /// Take the guarded value; this consuming the mutex, no thread can hold it.
+    #[inline] pub fn into_inner(self) -> T { self.valu.into_inner() }
+}
+
+impl<T: ?Sized, S: SystemSync> Mutex<T, S> {
+    /// Take an exclusive reference to the guarded value; this being `&mut self`, no thread can
+    /// hold the lock.
+    #[inline] pub fn get_mut(&mut self) -> &mut T { unsafe { &mut *self.valu.get() } }
+
     /// Take an exclusive reference to the guarded value, blocking if another thread is already
     /// holding it.
-    #[inline] pub fn lock(&self) -> Guard<T> {
+    #[inline] pub fn lock(&self) -> Guard<'_, T, S> {
         unsafe {
-            self.lock.lock(0x100);
+            S::lock(&self.lock);
             Guard {
                 lock: &self.lock,
                 valu: &mut *self.valu.get(),
+                unsend: PhantomData,
             }
         }
     }
 
+    /// Release the lock without its guard, e.g. where a C API calls back to take the lock and
+    /// again to release it, so the guard was forgotten in between.
+    ///
+    /// The calling thread need not be that which took the lock.
+    ///
+    /// ```
+    /// # extern crate user_sync;
+    /// use std::mem;
+    /// use user_sync::Mutex;
+    ///
+    /// # fn main() {
+    /// let mutex = Mutex::new(0);
+    /// mem::forget(mutex.lock());
+    /// unsafe { mutex.force_unlock() };
+    /// assert!(mutex.try_lock().is_some());
+    /// # }
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held, and no guard of it may be live nor dropped after, as the holder
+    /// thinks it has released the lock.
+    #[inline] pub unsafe fn force_unlock(&self) {
+        S::adopt(&self.lock);
+        S::unlock(&self.lock)
+    }
+
+    /// Make a guard of the lock, which some other means took, e.g. a C API calling back with it
+    /// held; the calling thread may then use the guarded value, and release the lock by dropping
+    /// the guard.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held, with no other guard of it live, and the holder must not release it
+    /// but by this guard.
+    #[inline] pub unsafe fn make_guard_unchecked(&self) -> Guard<'_, T, S> {
+        S::adopt(&self.lock);
+        Guard { lock: &self.lock, valu: &mut *self.valu.get(), unsend: PhantomData }
+    }
+
+    /// Whether some thread holds the lock
+    ///
+    /// This reads the lock without taking it, so unlike `try_lock` it doesn't disturb other
+    /// threads; but they may take or release it meanwhile, so this is only advisory, e.g. for
+    /// diagnostics or as `debug_assert!(mutex.is_locked())`.
+    #[inline] pub fn is_locked(&self) -> bool { S::is_locked(&self.lock) }
+
     /// Take an exclusive reference to the guarded value, returning `None` if another thread is
     /// already holding it.
-    #[inline] pub fn try_lock(&self) -> Option<Guard<T>> {
+    #[inline] pub fn try_lock(&self) -> Option<Guard<'_, T, S>> {
+        unsafe {
+            if S::try_lock(&self.lock) {
+                Some(Guard {
+                    lock: &self.lock,
+                    valu: &mut *self.valu.get(),
+                    unsend: PhantomData,
+                })
+            } else { None }
+        }
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Take an exclusive reference to the guarded value, blocking if another thread is already
+    /// holding it, unless and until `token` is canceled, whereupon return `None`.
+    ///
+    /// A blocked thread checks the token each millisecond or so.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline] pub fn lock_cancelable(&self, token: &CancelToken) -> Option<Guard<'_, T>> {
+        unsafe {
+            if self.lock.lock_cancelable(::default_policy(), &token.0) {
+                Some(Guard {
+                    lock: &self.lock,
+                    valu: &mut *self.valu.get(),
+                    unsend: PhantomData,
+                })
+            } else { None }
+        }
+    }
+
+    /// Take an exclusive reference to the guarded value, blocking if another thread is already
+    /// holding it, for at most `timeout`, whereupon return `None`.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline] pub fn try_lock_for(&self, timeout: Duration) -> Option<Guard<'_, T>> {
+        self.try_lock_until(Instant::now() + timeout)
+    }
+
+    /// As `try_lock_for`, but give up at `deadline`.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline] pub fn try_lock_until(&self, deadline: Instant) -> Option<Guard<'_, T>> {
+        unsafe {
+            if self.lock.lock_until(::default_policy(), deadline.0) {
+                Some(Guard {
+                    lock: &self.lock,
+                    valu: &mut *self.valu.get(),
+                    unsend: PhantomData,
+                })
+            } else { None }
+        }
+    }
+
+    /// Address of the futex word of the lock, e.g. to match to `futex` tracepoints
+    ///
+    /// This is only to identify the lock; writing through it would break the lock.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { self.lock.futex_addr() }
+
+    /// Thread holding the lock, if known
+    ///
+    /// This is only tracked in debug builds, or with the `deadlock-detection` feature; otherwise
+    /// it is always `None`.
+    #[inline] pub fn owner(&self) -> Option<ThreadId> {
+        match self.lock.owner() { 0 => None, tid => Some(ThreadId(tid)) }
+    }
+
+    /// List the lock in `dump_locks` under the name it was made with by `new_named`, for good;
+    /// a lock made otherwise, or listed already, is left as it is. It must be `'static`, as in a
+    /// `static`, so that it is never moved nor dropped while listed.
+    #[cfg(feature = "named-locks")]
+    #[inline] pub fn register(&'static self) { self.lock.register() }
+
+    /// As `lock`, but the guard holds a clone of the `Arc` rather than a borrow, so it is
+    /// `'static`, and may be moved into a closure or sent to another thread, which may then
+    /// release the lock.
+    #[cfg(feature = "alloc")]
+    #[inline] pub fn lock_arc(self: &Arc<Self>) -> ArcGuard<T> {
+        self.lock.lock(::default_policy());
+        ArcGuard { mutex: self.clone() }
+    }
+
+    /// As `try_lock`, but the guard is owned, as by `lock_arc`.
+    #[cfg(feature = "alloc")]
+    #[inline] pub fn try_lock_arc(self: &Arc<Self>) -> Option<ArcGuard<T>> {
+        if self.lock.try_lock() { Some(ArcGuard { mutex: self.clone() }) } else { None }
+    }
+
+    /// Whether some thread holds the lock and others are waiting for it; as `is_locked`, only
+    /// advisory
+    ///
+    /// This may be true a while after the last waiter stopped waiting, as the lock learns only
+    /// at release that none remain.
+    #[inline] pub fn is_contended(&self) -> bool { self.lock.is_contended() }
+
+    /// Counts of acquisitions of the lock so far
+    #[cfg(feature = "stats")]
+    #[inline] pub fn stats(&self) -> MutexStats {
+        let (fast, parked) = self.lock.stats();
+        MutexStats { fast, parked }
+    }
+
+    /// As `try_lock`, but may return `None` spuriously, even if no thread holds the lock, for a
+    /// cheaper failure on some architectures; it is meant to be called in a loop.
+    ///
+    /// A successful call orders the critical section after the last release of the lock, as
+    /// `lock` does; only a failure is unordered, and then the caller has touched nothing guarded.
+    #[inline] pub fn fast_try_lock(&self) -> Option<Guard<'_, T>> {
         unsafe {
-            if self.lock.try_lock() {
+            if self.lock.fast_try_lock() {
                 Some(Guard {
                     lock: &self.lock,
                     valu: &mut *self.valu.get(),
+                    unsend: PhantomData,
                 })
             } else { None }
         }
     }
 }
 
+/// Counts of acquisitions of a `Mutex`
+///
+/// The counters are updated with relaxed ordering and separately, so they are only approximate
+/// while other threads are using the lock.
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MutexStats {
+    /// Acquisitions by spinning or without contention
+    pub fast: u64,
+    /// Acquisitions which parked the thread
+    pub parked: u64,
+}
+
+/// Counts of system calls of a `CondVar`
+///
+/// The counter is updated with relaxed ordering, so it is only approximate while other threads
+/// are notifying.
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CondVarStats {
+    /// System calls made by notifications, to wake or move waiters
+    pub wake_calls: u64,
+}
+
 /// Exclusive reference to `Mutex`-guarded value
+///
+/// This is not `Send`, as the lock ought to be released by the thread which took it; where it
+/// must be released by another, convert it to a `SendGuard`.
+///
+/// ```compile_fail,E0277
+/// # extern crate user_sync;
+/// # fn main() {
+/// fn send<T: Send>(_: T) {}
+/// let mutex = user_sync::Mutex::new(0);
+/// send(mutex.lock());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Guard<'a, T: ?Sized + 'a, S: SystemSync + 'a = DefaultSystem> {
+    lock: &'a S::Mutex,
+    valu: &'a mut T,
+    unsend: PhantomData<*mut ()>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync, S: SystemSync> Sync for Guard<'a, T, S> {}
+
+impl<'a, T: ?Sized, S: SystemSync> Guard<'a, T, S> {
+    /// Make the guard sendable to another thread, which may then release the lock.
+    #[inline] pub fn into_send(this: Self) -> SendGuard<'a, T, S> {
+        let guard = SendGuard { lock: this.lock, valu: unsafe { &mut *(this.valu as *mut T) } };
+        mem::forget(this);
+        guard
+    }
+
+    /// Proof that the lock is held, for as long as the guard is borrowed
+    #[inline] pub fn token<'b>(_: &'b Self) -> Locked<'b, T> { Locked(PhantomData) }
+
+    /// Release the lock, run `f`, and take the lock again, e.g. around a long computation which
+    /// does not touch the guarded value. The lock is taken again even if `f` panicks.
+    #[inline] pub fn unlocked<R, F: FnOnce() -> R>(this: &mut Self, f: F) -> R {
+        unsafe { S::unlock(this.lock) };
+        let _relock = Relock::<S>(this.lock);
+        f()
+    }
+}
+
+impl<'a, T: ?Sized> Guard<'a, T> {
+    /// Release the lock, handing it to a waiting thread, if any, rather than letting any thread
+    /// take it; so a thread which would take it again at once goes behind those waiting. On
+    /// Windows, whose wakes don't tell whether they woke any thread, this merely releases it.
+    #[inline] pub fn unlock_fair(this: Self) {
+        this.lock.unlock_fair();
+        mem::forget(this);
+    }
+
+    /// If any thread waits for the lock, hand it to that thread, and take it again after; e.g.
+    /// periodically in a long loop holding the lock, to let waiters through; as `unlock_fair`,
+    /// not on Windows.
+    #[inline] pub fn bump(this: &mut Self) { this.lock.bump(::default_policy()) }
+}
+
+// Lock taken again when dropped, even by a panic
+struct Relock<'a, S: SystemSync + 'a>(&'a S::Mutex);
+
+impl<'a, S: SystemSync> Drop for Relock<'a, S> {
+    #[inline] fn drop(&mut self) { S::lock(self.0) }
+}
+
+/// Proof that a `Mutex<T>` is held
+///
+/// A function which must be called with the lock held may take this, rather than take the lock
+/// itself, which would deadlock.
+///
+/// ```
+/// # extern crate user_sync;
+/// use user_sync::{Guard, Locked, Mutex};
+///
+/// struct Config { verbose: bool }
+///
+/// fn log(_: Locked<Config>, msg: &str) { println!("{}", msg) }
+///
+/// # fn main() {
+/// let config = Mutex::new(Config { verbose: true });
+/// let guard = config.lock();
+/// if guard.verbose { log(Guard::token(&guard), "verbose") }
+/// # }
+/// ```
+///
+/// It can't outlive the guard:
+///
+/// ```compile_fail,E0716
+/// # extern crate user_sync;
+/// use user_sync::{Guard, Mutex};
+///
+/// # fn main() {
+/// let mutex = Mutex::new(());
+/// let token = Guard::token(&mutex.lock());
+/// drop(token);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Locked<'a, T: ?Sized + 'a>(PhantomData<&'a T>);
+
+impl<'a, T: ?Sized> Clone for Locked<'a, T> {
+    #[inline] fn clone(&self) -> Self { *self }
+}
+
+impl<'a, T: ?Sized> Copy for Locked<'a, T> {}
+
+impl<'a, T: ?Sized, S: SystemSync> Deref for Guard<'a, T, S> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { self.valu }
+}
+
+impl<'a, T: ?Sized, S: SystemSync> DerefMut for Guard<'a, T, S> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { self.valu }
+}
+
+impl<'a, T: ?Sized, S: SystemSync> Drop for Guard<'a, T, S> {
+    #[inline] fn drop(&mut self) { unsafe { S::unlock(self.lock) } }
+}
+
+/// Exclusive reference to `Mutex`-guarded value, which may be sent to another thread
 #[derive(Debug)]
-pub struct Guard<'a, T: ?Sized + 'a> {
-    lock: &'a ::system::Mutex,
+pub struct SendGuard<'a, T: ?Sized + 'a, S: SystemSync + 'a = DefaultSystem> {
+    lock: &'a S::Mutex,
     valu: &'a mut T,
 }
 
-unsafe impl<'a, T: ?Sized> Send for Guard<'a, T> {}
+unsafe impl<'a, T: ?Sized + Send, S: SystemSync> Send for SendGuard<'a, T, S> {}
 
-impl<'a, T: ?Sized> Deref for Guard<'a, T> {
+impl<'a, T: ?Sized, S: SystemSync> Deref for SendGuard<'a, T, S> {
     type Target = T;
     #[inline] fn deref(&self) -> &T { self.valu }
 }
 
-impl<'a, T: ?Sized> DerefMut for Guard<'a, T> {
+impl<'a, T: ?Sized, S: SystemSync> DerefMut for SendGuard<'a, T, S> {
     #[inline] fn deref_mut(&mut self) -> &mut T { self.valu }
 }
 
-impl<'a, T: ?Sized> Drop for Guard<'a, T> {
-    #[inline] fn drop(&mut self) { self.lock.unlock(0x100) }
+impl<'a, T: ?Sized, S: SystemSync> Drop for SendGuard<'a, T, S> {
+    #[inline] fn drop(&mut self) {
+        S::adopt(self.lock);
+        unsafe { S::unlock(self.lock) }
+    }
+}
+
+/// Exclusive reference to `Mutex`-guarded value, which owns a reference count of the mutex
+///
+/// ```
+/// # extern crate user_sync;
+/// use std::sync::Arc;
+/// use std::thread;
+/// use user_sync::Mutex;
+///
+/// # fn main() {
+/// let mutex = Arc::new(Mutex::new(0));
+/// let mut guard = mutex.lock_arc();
+/// thread::spawn(move || *guard += 1).join().unwrap();
+/// assert_eq!(1, *mutex.lock());
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct ArcGuard<T: ?Sized> {
+    mutex: Arc<Mutex<T>>,
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<T: ?Sized + Send> Send for ArcGuard<T> {}
+#[cfg(feature = "alloc")]
+unsafe impl<T: ?Sized + Send + Sync> Sync for ArcGuard<T> {}
+
+#[cfg(feature = "alloc")]
+impl<T: ?Sized> ArcGuard<T> {
+    /// The mutex which this guards
+    #[inline] pub fn mutex(this: &Self) -> &Arc<Mutex<T>> { &this.mutex }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: ?Sized> Deref for ArcGuard<T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { unsafe { &*self.mutex.valu.get() } }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: ?Sized> DerefMut for ArcGuard<T> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { unsafe { &mut *self.mutex.valu.get() } }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: ?Sized> Drop for ArcGuard<T> {
+    #[inline] fn drop(&mut self) {
+        self.mutex.lock.adopt();
+        self.mutex.lock.unlock(::default_policy())
+    }
 }
 
 /// Condition variable
 ///
 /// A condition variable lets a thread holding a lock awaiting some predicate of the guarded
 /// value to do so and not miss a notification that the predicate became true.
-/// It may not be used with multiple mutexen simultaneously; this is dynamically asserted.
+/// It may not be used with multiple mutexen simultaneously; this is dynamically asserted. It may
+/// be used with one mutex and then another, once no thread waits with the former.
+///
+/// A notification may be made with the lock held or not: a waiter reads the sequence number it
+/// waits on before it releases the lock, so if the predicate is made true under the lock, a
+/// notification made after that, in whichever thread, is not missed by a waiter which saw it
+/// false. Only a change made without the lock may be missed.
 ///
 /// Example:
 ///
@@ -99,25 +530,333 @@ impl<'a, T: ?Sized> Drop for Guard<'a, T> {
 ///     }
 /// }
 /// ```
+#[cfg(not(feature = "critical-section"))]
 #[derive(Debug)]
-pub struct CondVar(::system::CondVar);
+pub struct CondVar<S: SystemCondVar = DefaultSystem>(S::CondVar);
+
+#[cfg(not(feature = "critical-section"))]
+impl Default for CondVar {
+    #[inline] fn default() -> Self { CondVar::new() }
+}
 
+#[cfg(not(feature = "critical-section"))]
 impl CondVar {
     #[inline] pub fn new() -> Self { CondVar(::system::CondVar::new()) }
 
+    /// Make a condition variable whose `notify_one` and `notify_n` skip the system call when
+    /// every waiting thread was already woken and has yet to return, so a burst of
+    /// notifications wakes no more threads than are waiting.
+    ///
+    /// Each notification still makes a thread about to wait return, so none is lost; the cost
+    /// is a few more atomic operations in each wait.
+    #[inline] pub fn coalescing() -> Self { CondVar(::system::CondVar::with_coalescing(true)) }
+
+    /// Counts of notifications' system calls so far
+    #[cfg(feature = "stats")]
+    #[inline] pub fn stats(&self) -> CondVarStats { CondVarStats { wake_calls: self.0.stats() } }
+
+    /// As `wait`, but return `Err(Interrupted)` if a signal handler ran while the thread was
+    /// blocked, so that it may break out of its wait loop, e.g. to shut down.
+    /// The lock is taken again in either case.
+    ///
+    /// Only handlers installed without `SA_RESTART` interrupt the wait.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if the `CondVar` is already in use with another mutex.
+    #[inline] pub fn wait_interruptible<'a, T>(&self, guard: Guard<'a, T>)
+      -> Result<Guard<'a, T>, Interrupted<Guard<'a, T>>> {
+        if self.0.wait_interruptible(guard.lock) { Ok(guard) } else { Err(Interrupted(guard)) }
+    }
+
+    /// As `wait`, but wait at most `timeout`; return the guard, and whether the wait timed out
+    /// rather than being notified.
+    ///
+    /// As with `wait`, the guarded condition may not hold either way, so the caller must
+    /// recheck it.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if the `CondVar` is already in use with another mutex.
+    #[inline] pub fn wait_timeout<'a, T>(&self, guard: Guard<'a, T>, timeout: Duration)
+      -> (Guard<'a, T>, bool) {
+        self.wait_until(guard, Instant::now() + timeout)
+    }
+
+    /// As `wait_timeout`, but wait at most until `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if the `CondVar` is already in use with another mutex.
+    #[inline] pub fn wait_until<'a, T>(&self, guard: Guard<'a, T>, deadline: Instant)
+      -> (Guard<'a, T>, bool) {
+        let notified = self.0.wait_until(guard.lock, deadline.0);
+        (guard, !notified)
+    }
+
+    /// As `wait_while`, but wait at most `timeout` in all; return the guard, and whether the wait
+    /// timed out with `pred` still true.
+    ///
+    /// The deadline is fixed on entry, so spurious wakeups don't extend the wait.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if the `CondVar` is already in use with another mutex.
+    #[inline] pub fn wait_timeout_while<'a, T, F: FnMut(&mut T) -> bool>(&self,
+                                                                         guard: Guard<'a, T>,
+                                                                         timeout: Duration,
+                                                                         pred: F)
+      -> (Guard<'a, T>, bool) {
+        self.wait_while_until(guard, Instant::now() + timeout, pred)
+    }
+
+    /// As `wait_timeout_while`, but wait at most until `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if the `CondVar` is already in use with another mutex.
+    #[inline] pub fn wait_while_until<'a, T, F: FnMut(&mut T) -> bool>(&self,
+                                                                       mut guard: Guard<'a, T>,
+                                                                       deadline: Instant,
+                                                                       mut pred: F)
+      -> (Guard<'a, T>, bool) {
+        while pred(&mut *guard) {
+            if !self.0.wait_until(guard.lock, deadline.0) {
+                let timed_out = pred(&mut *guard);
+                return (guard, timed_out);
+            }
+        }
+        (guard, false)
+    }
+
+    /// As `notify_one`, with the lock held, but rather than wake the thread, move it to wait on
+    /// the lock, so it wakes once the caller releases the lock, rather than at once only to block
+    /// on it again.
+    #[inline] pub fn notify_one_locked<'a, T: ?Sized>(&self, guard: &Guard<'a, T>) -> usize {
+        self.0.notify_one_locked(guard.lock)
+    }
+
+    /// As `notify_one`, and then drop the guard, in one system call rather than two if other
+    /// threads wait for the lock too; return how many threads were woken in all.
+    #[inline] pub fn notify_one_unlock<'a, T: ?Sized>(&self, guard: Guard<'a, T>) -> usize {
+        let lock = guard.lock;
+        mem::forget(guard);
+        self.0.notify_one_unlock(lock)
+    }
+
+    /// Address of the futex word on which waiters block, e.g. to match to `futex` tracepoints
+    ///
+    /// This is only to identify the condition variable; writing through it would break it.
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { self.0.futex_addr() }
+
+    /// Forget the mutex this was used with, and any notifications not yet consumed.
+    ///
+    /// The mutex is forgotten anyway once no thread waits; this takes `&mut self` so none can be
+    /// waiting meanwhile.
+    #[inline] pub fn reset(&mut self) { self.0.reset() }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<S: SystemCondVar> CondVar<S> {
+    /// Make a condition variable over that of `S`; `new` is for `DefaultSystem`.
+    #[inline] pub const fn with_system() -> Self { CondVar(S::CONDVAR_INIT) }
+
     /// Atomically release the guard lock and wait for another thread to call `notify`.
     ///
     /// # Panics
     ///
     /// Panicks if the `CondVar` is already in use with another mutex.
-    #[inline] pub fn wait<'a, T>(&self, guard: Guard<'a, T>) -> Guard<'a, T> {
-        self.0.wait(guard.lock);
+    #[inline] pub fn wait<'a, T>(&self, guard: Guard<'a, T, S>) -> Guard<'a, T, S> {
+        unsafe { S::wait(&self.0, guard.lock) };
+        guard
+    }
+
+    /// Wait while `pred` of the guarded value is true, checking it before the first wait and
+    /// after each wakeup, spurious or not.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if the `CondVar` is already in use with another mutex.
+    #[inline] pub fn wait_while<'a, T, F: FnMut(&mut T) -> bool>(&self, mut guard: Guard<'a, T, S>,
+                                                                 mut pred: F) -> Guard<'a, T, S> {
+        while pred(&mut *guard) { guard = self.wait(guard) }
         guard
     }
 
-    /// Unblock one waiting thread.
-    #[inline] pub fn notify_one(&self) { self.0.notify_one() }
+    /// Wait until `pred` of the guarded value is true; the inverse of `wait_while`.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if the `CondVar` is already in use with another mutex.
+    #[inline] pub fn wait_until_true<'a, T, F: FnMut(&mut T) -> bool>(&self,
+                                                                      guard: Guard<'a, T, S>,
+                                                                      mut pred: F)
+      -> Guard<'a, T, S> {
+        self.wait_while(guard, |x| !pred(x))
+    }
+
+    /// Check once, without blocking, whether the caller would need to wait: return the guard in
+    /// `Err` if `pred` of the guarded value is true, else in `Ok`.
+    #[inline] pub fn try_wait_while<'a, T, F: FnMut(&mut T) -> bool>(&self,
+                                                                     mut guard: Guard<'a, T, S>,
+                                                                     mut pred: F)
+      -> Result<Guard<'a, T, S>, Guard<'a, T, S>> {
+        if pred(&mut *guard) { Err(guard) } else { Ok(guard) }
+    }
+
+    /// Unblock one waiting thread; return how many were notified, 0 or 1.
+    #[inline] pub fn notify_one(&self) -> usize { S::notify_one(&self.0) }
+
+    /// Unblock at most `n` waiting threads, e.g. one for each of `n` items made ready at once;
+    /// return how many were notified.
+    ///
+    /// Each waiter woken must recheck its predicate, as it may find that another thread took
+    /// what it was woken for.
+    #[inline] pub fn notify_n(&self, n: usize) -> usize { S::notify_n(&self.0, n) }
+
+    /// Unblock all waiting threads; return how many were notified.
+    #[inline] pub fn notify_all(&self) -> usize { S::notify_all(&self.0) }
+}
+
+/// Error of a wait cut short by a signal, holding what the wait would have returned
+#[cfg(not(feature = "critical-section"))]
+#[derive(Debug)]
+pub struct Interrupted<T>(pub T);
+
+/// Mutex and condition variable in one
+///
+/// Waits are made through the guard, so a wait always releases the mutex of the condition
+/// variable, and the two can't be mismatched.
+///
+/// ```
+/// # extern crate user_sync;
+/// use std::sync::Arc;
+/// use std::thread;
+/// use user_sync::{Monitor, MonitorGuard};
+///
+/// # fn main() {
+/// let ready = Arc::new(Monitor::new(false));
+/// let setter = {
+///     let ready = ready.clone();
+///     thread::spawn(move || {
+///         *ready.lock() = true;
+///         ready.notify_all();
+///     })
+/// };
+/// let guard = MonitorGuard::wait_while(ready.lock(), |ready| !*ready);
+/// assert!(*guard);
+/// # drop(guard);
+/// # setter.join().unwrap();
+/// # }
+/// ```
+#[cfg(not(feature = "critical-section"))]
+#[derive(Debug)]
+pub struct Monitor<T> {
+    mutex: Mutex<T>,
+    cond: CondVar,
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<T> Monitor<T> {
+    #[inline] pub fn new(x: T) -> Self { Monitor { mutex: Mutex::new(x), cond: CondVar::new() } }
+
+    /// Take an exclusive reference to the guarded value, blocking if another thread is already
+    /// holding it.
+    #[inline] pub fn lock(&self) -> MonitorGuard<'_, T> {
+        MonitorGuard { guard: self.mutex.lock(), cond: &self.cond }
+    }
+
+    /// Take an exclusive reference to the guarded value, returning `None` if another thread is
+    /// already holding it.
+    #[inline] pub fn try_lock(&self) -> Option<MonitorGuard<'_, T>> {
+        self.mutex.try_lock().map(|guard| MonitorGuard { guard, cond: &self.cond })
+    }
+
+    /// Unblock one waiting thread; return how many were notified, 0 or 1. As the lock is not
+    /// held, this wakes the thread at once, unlike `MonitorGuard::notify_one`, which, the lock
+    /// held, moves it to wait on the lock.
+    #[inline] pub fn notify_one(&self) -> usize { self.cond.notify_one() }
+
+    /// Unblock at most `n` waiting threads; return how many were notified.
+    #[inline] pub fn notify_n(&self, n: usize) -> usize { self.cond.notify_n(n) }
+
+    /// Unblock all waiting threads; return how many were notified.
+    #[inline] pub fn notify_all(&self) -> usize { self.cond.notify_all() }
+}
+
+/// Exclusive reference to `Monitor`-guarded value, through which to wait for a notification
+#[cfg(not(feature = "critical-section"))]
+#[derive(Debug)]
+pub struct MonitorGuard<'a, T: 'a> {
+    guard: Guard<'a, T>,
+    cond: &'a CondVar,
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T> MonitorGuard<'a, T> {
+    /// Atomically release the lock and wait for another thread to call `notify`; as
+    /// `CondVar::wait`.
+    #[inline] pub fn wait(this: Self) -> Self {
+        let MonitorGuard { guard, cond } = this;
+        MonitorGuard { guard: cond.wait(guard), cond }
+    }
+
+    /// Wait while `pred` of the guarded value is true; as `CondVar::wait_while`.
+    #[inline] pub fn wait_while<F: FnMut(&mut T) -> bool>(this: Self, pred: F) -> Self {
+        let MonitorGuard { guard, cond } = this;
+        MonitorGuard { guard: cond.wait_while(guard, pred), cond }
+    }
+
+    /// Wait at most `timeout`; as `CondVar::wait_timeout`.
+    #[inline] pub fn wait_timeout(this: Self, timeout: Duration) -> (Self, bool) {
+        let MonitorGuard { guard, cond } = this;
+        let (guard, timed_out) = cond.wait_timeout(guard, timeout);
+        (MonitorGuard { guard, cond }, timed_out)
+    }
+
+    /// Wait at most until `deadline`; as `CondVar::wait_until`.
+    #[inline] pub fn wait_until(this: Self, deadline: Instant) -> (Self, bool) {
+        let MonitorGuard { guard, cond } = this;
+        let (guard, timed_out) = cond.wait_until(guard, deadline);
+        (MonitorGuard { guard, cond }, timed_out)
+    }
+
+    /// Wait while `pred` of the guarded value is true, for at most `timeout` in all; as
+    /// `CondVar::wait_timeout_while`.
+    #[inline] pub fn wait_timeout_while<F: FnMut(&mut T) -> bool>(this: Self, timeout: Duration,
+                                                                  pred: F) -> (Self, bool) {
+        let MonitorGuard { guard, cond } = this;
+        let (guard, timed_out) = cond.wait_timeout_while(guard, timeout, pred);
+        (MonitorGuard { guard, cond }, timed_out)
+    }
+
+    /// Wait while `pred` of the guarded value is true, at most until `deadline`; as
+    /// `CondVar::wait_while_until`.
+    #[inline] pub fn wait_while_until<F: FnMut(&mut T) -> bool>(this: Self, deadline: Instant,
+                                                                pred: F) -> (Self, bool) {
+        let MonitorGuard { guard, cond } = this;
+        let (guard, timed_out) = cond.wait_while_until(guard, deadline, pred);
+        (MonitorGuard { guard, cond }, timed_out)
+    }
+
+    /// Unblock one waiting thread, which wakes once this is dropped; as
+    /// `CondVar::notify_one_locked`.
+    #[inline] pub fn notify_one(this: &Self) -> usize { this.cond.notify_one_locked(&this.guard) }
+
+    /// Unblock one waiting thread, and release the lock; as `CondVar::notify_one_unlock`.
+    #[inline] pub fn notify_one_unlock(this: Self) -> usize {
+        let MonitorGuard { guard, cond } = this;
+        cond.notify_one_unlock(guard)
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T> Deref for MonitorGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { &self.guard }
+}
 
-    /// Unblock all waiting threads.
-    #[inline] pub fn notify_all(&self) { self.0.notify_all() }
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T> DerefMut for MonitorGuard<'a, T> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { &mut self.guard }
 }
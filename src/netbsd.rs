@@ -0,0 +1,207 @@
+//! NetBSD system calls
+//!
+//! NetBSD's `__futex` call takes the operations of Linux's by the same numbers, so the futex
+//! backend runs on it as on Linux, rather than on `___lwp_park`, which parks a thread by its
+//! identifier rather than by a word. It is made through the C library, as the `syscall` crate is
+//! for Linux alone, and takes the second count of an operation by its own argument rather than in
+//! place of the timeout.
+
+use core::ptr;
+use core::sync::atomic::AtomicU32;
+use core::time::Duration;
+
+#[repr(C)]
+struct Timespec {
+    tv_sec: i64,
+    tv_nsec: isize,
+}
+
+extern "C" {
+    fn syscall(number: i32, ...) -> i32;
+    fn __errno() -> *mut i32;
+    fn _lwp_self() -> i32;
+    fn sched_yield() -> i32;
+    fn clock_gettime(clock: i32, ts: *mut Timespec) -> i32;
+}
+
+const SYS___FUTEX: i32 = 166;
+
+const FUTEX_WAIT: i32 = 0;
+const FUTEX_WAKE: i32 = 1;
+const FUTEX_CMP_REQUEUE: i32 = 4;
+const FUTEX_WAKE_OP: i32 = 5;
+const FUTEX_WAIT_BITSET: i32 = 9;
+const FUTEX_PRIVATE_FLAG: i32 = 128;
+const FUTEX_BITSET_MATCH_ANY: u32 = !0;
+
+const FUTEX_OP_SET: u32 = 0;
+const FUTEX_OP_OR: u32 = 2;
+const FUTEX_OP_CMP_EQ: u32 = 0;
+const FUTEX_OP_CMP_GE: u32 = 5;
+
+const EINTR: i32 = 4;
+const EAGAIN: i32 = 35;
+const ETIMEDOUT: i32 = 60;
+
+const CLOCK_MONOTONIC: i32 = 3;
+
+#[inline] const fn futex_op(op: u32, oparg: u32, cmp: u32, cmparg: u32) -> u32 {
+    (op & 0xf) << 28 | (cmp & 0xf) << 24 | (oparg & 0xfff) << 12 | cmparg & 0xfff
+}
+
+// Operation for `FUTEX_WAKE_OP` which leaves the second word as it is, and wakes its waiters
+// whatever it holds
+const WAKE_ALWAYS: u32 = futex_op(FUTEX_OP_OR, 0, FUTEX_OP_CMP_GE, 0);
+
+// The call takes counts as `int`.
+#[inline] fn count(n: usize) -> i32 { n.min(i32::MAX as usize) as i32 }
+
+// The call returns -1 and sets `errno` on failure.
+#[inline] fn check(r: i32) -> Result<usize, i32> {
+    if r < 0 { Err(unsafe { *__errno() }) } else { Ok(r as usize) }
+}
+
+#[inline] unsafe fn futex(f: *const AtomicU32, op: i32, val: i32, timeout: *const Timespec,
+                          f2: *const AtomicU32, val2: i32, val3: u32) -> Result<usize, i32> {
+    check(syscall(SYS___FUTEX, f, op, val, timeout, f2, val2, val3))
+}
+
+// As on Linux, restart a wait interrupted by a signal handler; the timed wait takes an absolute
+// deadline, so a restarted one ends when the first would have.
+#[inline] fn wait_flags(f: &AtomicU32, val: u32, flags: i32) {
+    loop {
+        match unsafe { futex(f, FUTEX_WAIT | flags, val as i32, ptr::null(), ptr::null(), 0, 0) } {
+            Err(EINTR) => (),
+            r => {
+                debug_assert!(r.is_ok() || r == Err(EAGAIN), "futex wait failed: error {:?}", r);
+                return;
+            },
+        }
+    }
+}
+
+#[inline]
+fn wait_until_flags(f: &AtomicU32, val: u32, deadline: u64, flags: i32) -> Result<bool, i32> {
+    let ts = Timespec { tv_sec: (deadline / 1_000_000_000) as i64,
+                        tv_nsec: (deadline % 1_000_000_000) as isize };
+    loop {
+        match unsafe {
+            futex(f, FUTEX_WAIT_BITSET | flags, val as i32, &ts, ptr::null(), 0,
+                  FUTEX_BITSET_MATCH_ANY)
+        } {
+            Err(EINTR) => (),
+            Err(ETIMEDOUT) => return Ok(false),
+            Ok(_) | Err(EAGAIN) => return Ok(true),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[inline] fn wake(f: &AtomicU32, n: usize, flags: i32) -> usize {
+    unsafe { futex(f, FUTEX_WAKE | flags, count(n), ptr::null(), ptr::null(), 0, 0) }
+        .unwrap_or(0)
+}
+
+#[inline] fn wake_op(f1: &AtomicU32, n1: usize, f2: &AtomicU32, n2: usize, op: u32) -> usize {
+    unsafe {
+        futex(f1, FUTEX_WAKE_OP | FUTEX_PRIVATE_FLAG, count(n1), ptr::null(), f2, count(n2), op)
+    }.unwrap_or(0)
+}
+
+/// Identifier of the calling thread
+#[inline]
+pub fn gettid() -> u32 { unsafe { _lwp_self() as u32 } }
+
+/// Let another thread run.
+#[inline]
+pub fn yield_now() { unsafe { sched_yield(); } }
+
+/// Nanoseconds on the monotonic clock
+#[inline]
+pub fn monotonic_now() -> u64 {
+    let mut ts = Timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { clock_gettime(CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Block while `*f == val`.
+///
+/// As on Linux, may return spuriously, so the caller must recheck its condition.
+#[inline]
+pub fn futex_wait(f: &AtomicU32, val: u32) { wait_flags(f, val, FUTEX_PRIVATE_FLAG) }
+
+/// Block while `*f == val`, for at most `timeout`; return `false` if it timed out.
+#[inline]
+pub fn futex_wait_timeout(f: &AtomicU32, val: u32, timeout: Duration) -> Result<bool, i32> {
+    let nanos = timeout.as_secs().saturating_mul(1_000_000_000)
+                       .saturating_add(timeout.subsec_nanos() as u64);
+    futex_wait_until(f, val, monotonic_now().saturating_add(nanos))
+}
+
+/// As `futex_wait`, but return `false` if a signal handler ran meanwhile.
+#[inline]
+pub fn futex_wait_interruptible(f: &AtomicU32, val: u32) -> bool {
+    let op = FUTEX_WAIT | FUTEX_PRIVATE_FLAG;
+    match unsafe { futex(f, op, val as i32, ptr::null(), ptr::null(), 0, 0) } {
+        Err(EINTR) => false,
+        r => {
+            debug_assert!(r.is_ok() || r == Err(EAGAIN), "futex wait failed: error {:?}", r);
+            true
+        },
+    }
+}
+
+/// Block while `*f == val`, until `deadline`, in nanoseconds on the monotonic clock.
+///
+/// Returns `Ok(false)` if the deadline passed, else as `futex_wait`.
+#[inline]
+pub fn futex_wait_until(f: &AtomicU32, val: u32, deadline: u64) -> Result<bool, i32> {
+    wait_until_flags(f, val, deadline, FUTEX_PRIVATE_FLAG)
+}
+
+// A timed wait of the crate's own primitives which failed would fail again if retried, and is
+// no timeout, so panic.
+#[cold]
+pub(crate) fn wait_failed(e: i32) -> ! { panic!("futex wait failed: error {}", e) }
+
+#[inline]
+pub(crate) fn futex_wait_shared(f: &AtomicU32, val: u32) { wait_flags(f, val, 0) }
+
+#[inline]
+pub(crate) fn futex_wait_until_shared(f: &AtomicU32, val: u32,
+                                      deadline: u64) -> Result<bool, i32> {
+    wait_until_flags(f, val, deadline, 0)
+}
+
+#[inline]
+pub(crate) fn futex_wake_shared(f: &AtomicU32, n: usize) -> usize { wake(f, n, 0) }
+
+/// Unblock at most `n` threads waiting on `f`; return how many were unblocked.
+#[inline]
+pub fn futex_wake(f: &AtomicU32, n: usize) -> usize { wake(f, n, FUTEX_PRIVATE_FLAG) }
+
+// Store `val`, at most 12 bits, in `f`, and unblock a thread waiting on it; the kernel does
+// both, so the caller never touches `f` after the store.
+#[inline]
+pub(crate) fn futex_store_wake(f: &AtomicU32, val: u32) {
+    wake_op(f, 1, f, 0, futex_op(FUTEX_OP_SET, val, FUTEX_OP_CMP_EQ, 0));
+}
+
+// Unblock a thread waiting on each of `f1` and `f2`, in one system call; return how many were
+// unblocked in all.
+#[inline]
+pub(crate) fn futex_wake_both(f1: &AtomicU32, f2: &AtomicU32) -> usize {
+    wake_op(f1, 1, f2, 1, WAKE_ALWAYS)
+}
+
+#[inline]
+pub(crate) fn cmp_requeue(f1: &AtomicU32, val: u32, n_wake: usize,
+                          f2: *const AtomicU32, n_requeue: usize) -> Option<usize> {
+    match unsafe {
+        futex(f1, FUTEX_CMP_REQUEUE | FUTEX_PRIVATE_FLAG, count(n_wake), ptr::null(), f2,
+              count(n_requeue), val)
+    } {
+        Err(EAGAIN) => None,
+        r => Some(r.unwrap_or(0)),
+    }
+}
@@ -0,0 +1,190 @@
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::mem;
+use core::ops::Deref;
+use core::time::Duration;
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wait_until, futex_wake, wait_failed};
+use time::Instant;
+
+const INCOMPLETE: u32 = 0;
+const RUNNING: u32 = 1;
+const RUNNING_WAITED: u32 = 2;
+const COMPLETE: u32 = 3;
+
+/// One-time initialization
+///
+/// Of threads calling `call_once`, one runs its closure, and the rest block until it returns.
+/// If it panics, the `Once` is left incomplete, and another thread runs its own closure.
+#[derive(Debug)]
+pub struct Once(AtomicU32);
+
+/// What a call to `Once::call_once_timeout` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnceResult {
+    /// The caller ran its closure, to completion.
+    Ran,
+    /// Another call completed, before the caller came or while it waited.
+    Completed,
+    /// The caller gave up waiting for another thread running its closure, which it may yet
+    /// complete; the caller's closure was not run.
+    TimedOut,
+}
+
+impl Default for Once {
+    #[inline] fn default() -> Self { Once::new() }
+}
+
+impl Once {
+    #[inline] pub const fn new() -> Self { Once(AtomicU32::new(INCOMPLETE)) }
+
+    /// Run `f` if no call has yet completed, else block until one has.
+    #[inline] pub fn call_once<F: FnOnce()>(&self, f: F) {
+        if COMPLETE == self.0.load(Memord::Acquire) { return };
+        self.call_once_slow(None, f);
+    }
+
+    /// As `call_once`, but if another thread is running its closure, wait for it at most
+    /// `timeout`, whereupon return `TimedOut` without running `f`.
+    ///
+    /// Only a waiter times out: once the caller runs `f`, it runs it to completion however long
+    /// it takes, as do threads running their closures by `call_once`.
+    #[inline] pub fn call_once_timeout<F: FnOnce()>(&self, timeout: Duration,
+                                                    f: F) -> OnceResult {
+        if COMPLETE == self.0.load(Memord::Acquire) { return OnceResult::Completed };
+        self.call_once_slow(Some(Instant::now() + timeout), f)
+    }
+
+    #[cold]
+    fn call_once_slow<F: FnOnce()>(&self, deadline: Option<Instant>, f: F) -> OnceResult {
+        let mut state = self.0.load(Memord::Acquire);
+        loop {
+            match state {
+                COMPLETE => return OnceResult::Completed,
+                INCOMPLETE => match self.0.compare_exchange(INCOMPLETE, RUNNING, Memord::Acquire,
+                                                            Memord::Acquire) {
+                    Ok(_) => break,
+                    Err(s) => state = s,
+                },
+                _ => {
+                    if RUNNING == state {
+                        let _ = self.0.compare_exchange(RUNNING, RUNNING_WAITED, Memord::Relaxed,
+                                                        Memord::Relaxed);
+                    }
+                    let timed_out = match deadline {
+                        None => { futex_wait(&self.0, RUNNING_WAITED); false },
+                        Some(t) => !futex_wait_until(&self.0, RUNNING_WAITED, t.0)
+                                        .unwrap_or_else(|e| wait_failed(e)),
+                    };
+                    state = self.0.load(Memord::Acquire);
+                    if timed_out && COMPLETE != state { return OnceResult::TimedOut };
+                },
+            }
+        }
+
+        let running = Running(&self.0);
+        f();
+        mem::forget(running);
+        if RUNNING_WAITED == self.0.swap(COMPLETE, Memord::Release) { futex_wake(&self.0, !0); }
+        OnceResult::Ran
+    }
+
+    /// Whether a call has completed
+    #[inline] pub fn is_completed(&self) -> bool { COMPLETE == self.0.load(Memord::Acquire) }
+}
+
+// Closure being run, which panicked if this is dropped
+struct Running<'a>(&'a AtomicU32);
+
+impl<'a> Drop for Running<'a> {
+    #[inline] fn drop(&mut self) {
+        if RUNNING_WAITED == self.0.swap(INCOMPLETE, Memord::Relaxed) { futex_wake(self.0, !0); }
+    }
+}
+
+/// Cell which is written at most once
+///
+/// Of threads calling `get_or_init`, one runs its closure to make the value, and the rest block
+/// until it is made.
+#[derive(Debug)]
+pub struct OnceCell<T> {
+    once: Once,
+    valu: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Send for OnceCell<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+
+impl<T> Default for OnceCell<T> {
+    #[inline] fn default() -> Self { OnceCell::new() }
+}
+
+impl<T> OnceCell<T> {
+    #[inline] pub const fn new() -> Self {
+        OnceCell { once: Once::new(), valu: UnsafeCell::new(None) }
+    }
+
+    /// The value, if set
+    #[inline] pub fn get(&self) -> Option<&T> {
+        if self.once.is_completed() { unsafe { (*self.valu.get()).as_ref() } } else { None }
+    }
+
+    /// Set the value to `x`, unless already set, whereupon return `x` in `Err`.
+    #[inline] pub fn set(&self, x: T) -> Result<(), T> {
+        let mut x = Some(x);
+        self.once.call_once(|| unsafe { *self.valu.get() = x.take() });
+        match x { None => Ok(()), Some(x) => Err(x) }
+    }
+
+    /// The value, which `f` makes if not yet set
+    #[inline] pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+        self.once.call_once(|| unsafe { *self.valu.get() = Some(f()) });
+        unsafe { (*self.valu.get()).as_ref().unwrap() }
+    }
+}
+
+/// Value made on first use, e.g. in a `static`
+///
+/// ```
+/// # extern crate user_sync;
+/// use user_sync::Lazy;
+///
+/// static TABLE: Lazy<[u32; 0x100]> = Lazy::new(|| {
+///     let mut table = [0; 0x100];
+///     for (k, x) in table.iter_mut().enumerate() { *x = (k * k) as u32 }
+///     table
+/// });
+///
+/// # fn main() {
+/// assert_eq!(0x51, TABLE[9]);
+/// # }
+/// ```
+pub struct Lazy<T, F = fn() -> T> {
+    cell: OnceCell<T>,
+    init: Cell<Option<F>>,
+}
+
+unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
+
+impl<T: fmt::Debug, F> fmt::Debug for Lazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lazy").field("cell", &self.cell).finish()
+    }
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    #[inline] pub const fn new(f: F) -> Self {
+        Lazy { cell: OnceCell::new(), init: Cell::new(Some(f)) }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T {
+        self.cell.get_or_init(|| match self.init.take() {
+            Some(f) => f(),
+            None => panic!("Lazy value's initializer panicked before"),
+        })
+    }
+}
@@ -0,0 +1,170 @@
+//! One-shot channel
+//!
+//! A `Oneshot` is split into a `Sender`, which may send one value, and a `Receiver`, which
+//! blocks until it arrives. If the sender is dropped without sending, the receiver gets
+//! `Canceled` instead.
+//!
+//! With the `alloc` feature, `channel` makes a slot on the heap, with halves which own it, e.g. to
+//! return a result from a spawned thread.
+//!
+//! ```
+//! # extern crate user_sync;
+//! use user_sync::oneshot::{Canceled, Oneshot};
+//!
+//! # fn main() {
+//! let mut chan = Oneshot::new();
+//! let (tx, rx) = chan.split();
+//! tx.send(42);
+//! assert_eq!(Ok(42), rx.recv());
+//!
+//! let (tx, rx) = chan.split();
+//! drop(tx);
+//! assert_eq!(Err(Canceled), rx.recv());
+//! # }
+//! ```
+
+#[cfg(feature = "alloc")] use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+
+const EMPTY: u32 = 0;
+const FULL: u32 = 1;
+const CLOSED: u32 = 2;
+
+/// Slot for one value, to be handed from one thread to another
+#[derive(Debug)]
+pub struct Oneshot<T> {
+    state: AtomicU32,
+    valu: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Sync for Oneshot<T> {}
+
+impl<T> Default for Oneshot<T> {
+    #[inline] fn default() -> Self { Oneshot::new() }
+}
+
+impl<T> Oneshot<T> {
+    #[inline] pub const fn new() -> Self {
+        Oneshot { state: AtomicU32::new(EMPTY), valu: UnsafeCell::new(None) }
+    }
+
+    /// Split into the sending and receiving halves, dropping any value left unreceived from
+    /// before; so the slot may be used again once both are dropped.
+    #[inline] pub fn split(&mut self) -> (Sender<'_, T>, Receiver<'_, T>) {
+        *self.state.get_mut() = EMPTY;
+        unsafe { *self.valu.get() = None };
+        (Sender { chan: self }, Receiver { chan: self })
+    }
+
+    #[inline] fn send(&self, x: T) {
+        unsafe { *self.valu.get() = Some(x) };
+        self.state.store(FULL, Memord::Release);
+        futex_wake(&self.state, 1);
+    }
+
+    #[inline] fn close(&self) {
+        if self.state.compare_exchange(EMPTY, CLOSED, Memord::Relaxed, Memord::Relaxed).is_ok() {
+            futex_wake(&self.state, 1);
+        }
+    }
+
+    #[inline] fn recv(&self) -> Result<T, Canceled> {
+        loop {
+            match self.state.load(Memord::Acquire) {
+                EMPTY => futex_wait(&self.state, EMPTY),
+                FULL => return Ok(unsafe { (*self.valu.get()).take().unwrap() }),
+                _ => return Err(Canceled),
+            }
+        }
+    }
+}
+
+/// Sending half of a `Oneshot`
+#[derive(Debug)]
+pub struct Sender<'a, T: 'a> {
+    chan: &'a Oneshot<T>,
+}
+
+impl<'a, T> Sender<'a, T> {
+    /// Send `x`, and unblock the receiver.
+    #[inline] pub fn send(self, x: T) { self.chan.send(x) }
+}
+
+impl<'a, T> Drop for Sender<'a, T> {
+    #[inline] fn drop(&mut self) { self.chan.close() }
+}
+
+/// Receiving half of a `Oneshot`
+#[derive(Debug)]
+pub struct Receiver<'a, T: 'a> {
+    chan: &'a Oneshot<T>,
+}
+
+impl<'a, T> Receiver<'a, T> {
+    /// Block until the value is sent, or return `Canceled` if the sender was dropped unsent.
+    #[inline] pub fn recv(self) -> Result<T, Canceled> { self.chan.recv() }
+}
+
+/// Make a one-shot channel on the heap, and return its sending and receiving halves.
+///
+/// ```
+/// # extern crate user_sync;
+/// use std::thread;
+/// use user_sync::oneshot;
+///
+/// # fn main() {
+/// let (tx, rx) = oneshot::channel();
+/// thread::spawn(move || tx.send(6 * 7));
+/// assert_eq!(Ok(42), rx.recv());
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[inline] pub fn channel<T>() -> (ArcSender<T>, ArcReceiver<T>) {
+    let chan = Arc::new(Oneshot::new());
+    (ArcSender { chan: chan.clone() }, ArcReceiver { chan })
+}
+
+/// Sending half of a `channel`
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct ArcSender<T> {
+    chan: Arc<Oneshot<T>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T> ArcSender<T> {
+    /// Send `x`, and unblock the receiver.
+    #[inline] pub fn send(self, x: T) { self.chan.send(x) }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Drop for ArcSender<T> {
+    #[inline] fn drop(&mut self) { self.chan.close() }
+}
+
+/// Receiving half of a `channel`
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct ArcReceiver<T> {
+    chan: Arc<Oneshot<T>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T> ArcReceiver<T> {
+    /// Block until the value is sent, or return `Canceled` if the sender was dropped unsent.
+    #[inline] pub fn recv(self) -> Result<T, Canceled> { self.chan.recv() }
+}
+
+/// Error of a `Receiver` whose `Sender` was dropped without sending
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Canceled;
+
+impl fmt::Display for Canceled {
+    #[inline] fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("sender dropped")
+    }
+}
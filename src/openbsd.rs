@@ -0,0 +1,176 @@
+//! OpenBSD system calls
+//!
+//! OpenBSD's `futex` call waits and wakes as Linux's, so the futex backend runs on it as it is;
+//! but it can't compare the word as it moves waiters between words, so it doesn't move them, nor
+//! can it modify a word as it wakes.
+
+use core::ptr;
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+use core::time::Duration;
+
+#[repr(C)]
+struct Timespec {
+    tv_sec: i64,
+    tv_nsec: isize,
+}
+
+extern "C" {
+    fn futex(f: *const AtomicU32, op: i32, val: i32, timeout: *const Timespec,
+             f2: *const AtomicU32) -> i32;
+    fn __errno() -> *mut i32;
+    fn getthrid() -> i32;
+    fn sched_yield() -> i32;
+    fn clock_gettime(clock: i32, ts: *mut Timespec) -> i32;
+}
+
+const FUTEX_WAIT: i32 = 1;
+const FUTEX_WAKE: i32 = 2;
+const FUTEX_PRIVATE_FLAG: i32 = 128;
+
+const EINTR: i32 = 4;
+const EAGAIN: i32 = 35;
+const ETIMEDOUT: i32 = 60;
+// A signal handler ran, which was installed with `SA_RESTART`
+const ECANCELED: i32 = 88;
+
+const CLOCK_MONOTONIC: i32 = 3;
+
+// The call takes counts as `int`.
+#[inline] fn count(n: usize) -> i32 { n.min(i32::MAX as usize) as i32 }
+
+#[inline] fn errno() -> i32 { unsafe { *__errno() } }
+
+// Block while `*f == val`, for at most `timeout`, or until woken if `None`; return `false` if it
+// timed out. A signal makes a spurious return, as on Linux.
+#[inline] fn wait(f: &AtomicU32, val: u32, timeout: Option<u64>,
+                  flags: i32) -> Result<bool, i32> {
+    let ts = timeout.map(|t| Timespec { tv_sec: (t / 1_000_000_000) as i64,
+                                        tv_nsec: (t % 1_000_000_000) as isize });
+    let ts = ts.as_ref().map_or(ptr::null(), |ts| ts as *const Timespec);
+    if 0 == unsafe { futex(f, FUTEX_WAIT | flags, val as i32, ts, ptr::null()) } {
+        return Ok(true);
+    }
+    match errno() {
+        ETIMEDOUT => Ok(false),
+        EAGAIN | EINTR | ECANCELED => Ok(true),
+        e => Err(e),
+    }
+}
+
+#[inline] fn wait_until(f: &AtomicU32, val: u32, deadline: u64,
+                        flags: i32) -> Result<bool, i32> {
+    loop {
+        let now = monotonic_now();
+        if now >= deadline { return Ok(false) };
+        if wait(f, val, Some(deadline - now), flags)? { return Ok(true) };
+    }
+}
+
+#[inline] fn wake(f: &AtomicU32, n: usize, flags: i32) -> usize {
+    match unsafe { futex(f, FUTEX_WAKE | flags, count(n), ptr::null(), ptr::null()) } {
+        r if r >= 0 => r as usize,
+        _ => 0,
+    }
+}
+
+/// Identifier of the calling thread
+#[inline]
+pub fn gettid() -> u32 { unsafe { getthrid() as u32 } }
+
+/// Let another thread run.
+#[inline]
+pub fn yield_now() { unsafe { sched_yield(); } }
+
+/// Nanoseconds on the monotonic clock
+#[inline]
+pub fn monotonic_now() -> u64 {
+    let mut ts = Timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { clock_gettime(CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Block while `*f == val`.
+///
+/// As on Linux, may return spuriously, so the caller must recheck its condition.
+#[inline]
+pub fn futex_wait(f: &AtomicU32, val: u32) {
+    let r = wait(f, val, None, FUTEX_PRIVATE_FLAG);
+    debug_assert!(r.is_ok(), "futex wait failed: error {:?}", r);
+}
+
+/// Block while `*f == val`, for at most `timeout`; return `false` if it timed out.
+#[inline]
+pub fn futex_wait_timeout(f: &AtomicU32, val: u32, timeout: Duration) -> Result<bool, i32> {
+    let nanos = timeout.as_secs().saturating_mul(1_000_000_000)
+                       .saturating_add(timeout.subsec_nanos() as u64);
+    futex_wait_until(f, val, monotonic_now().saturating_add(nanos))
+}
+
+/// As `futex_wait`, but return `false` if a signal interrupted it.
+#[inline]
+pub fn futex_wait_interruptible(f: &AtomicU32, val: u32) -> bool {
+    let r = unsafe { futex(f, FUTEX_WAIT | FUTEX_PRIVATE_FLAG, val as i32, ptr::null(),
+                           ptr::null()) };
+    match if 0 == r { 0 } else { errno() } {
+        EINTR | ECANCELED => false,
+        e => { debug_assert!(0 == e || EAGAIN == e, "futex wait failed: error {}", e); true },
+    }
+}
+
+/// Block while `*f == val`, until `deadline`, in nanoseconds on the monotonic clock.
+///
+/// Returns `Ok(false)` if the deadline passed, else as `futex_wait`. The call takes a timeout
+/// rather than a deadline, so this waits again for what remains of it after a spurious return.
+#[inline]
+pub fn futex_wait_until(f: &AtomicU32, val: u32, deadline: u64) -> Result<bool, i32> {
+    wait_until(f, val, deadline, FUTEX_PRIVATE_FLAG)
+}
+
+// A timed wait of the crate's own primitives which failed would fail again if retried, and is
+// no timeout, so panic.
+#[cold]
+pub(crate) fn wait_failed(e: i32) -> ! { panic!("futex wait failed: error {}", e) }
+
+#[inline]
+pub(crate) fn futex_wait_shared(f: &AtomicU32, val: u32) {
+    let r = wait(f, val, None, 0);
+    debug_assert!(r.is_ok(), "futex wait failed: error {:?}", r);
+}
+
+#[inline]
+pub(crate) fn futex_wait_until_shared(f: &AtomicU32, val: u32,
+                                      deadline: u64) -> Result<bool, i32> {
+    wait_until(f, val, deadline, 0)
+}
+
+#[inline]
+pub(crate) fn futex_wake_shared(f: &AtomicU32, n: usize) -> usize { wake(f, n, 0) }
+
+/// Unblock at most `n` threads waiting on `f`; return how many were unblocked.
+#[inline]
+pub fn futex_wake(f: &AtomicU32, n: usize) -> usize { wake(f, n, FUTEX_PRIVATE_FLAG) }
+
+// Store `val` in `f`, and unblock a thread waiting on it; the wake only keys the waiter by the
+// address of `f`, so it needn't be valid by then.
+#[inline]
+pub(crate) fn futex_store_wake(f: &AtomicU32, val: u32) {
+    let p = f as *const AtomicU32;
+    f.store(val, Memord::Release);
+    unsafe { futex(p, FUTEX_WAKE | FUTEX_PRIVATE_FLAG, 1, ptr::null(), ptr::null()) };
+}
+
+// Unblock a thread waiting on each of `f1` and `f2`; return how many were unblocked.
+#[inline]
+pub(crate) fn futex_wake_both(f1: &AtomicU32, f2: &AtomicU32) -> usize {
+    futex_wake(f1, 1) + futex_wake(f2, 1)
+}
+
+// The kernel's requeue doesn't compare `*f1` to `val`, and comparing it first would race with
+// `CondVar::enter` bumping it as the CondVar is rebound to another mutex, whereupon this would
+// move waiters of that mutex to wait on `f2`; so unblock the threads rather than move them, and
+// they block on the mutex they hold as they take it, if they must.
+#[inline]
+pub(crate) fn cmp_requeue(f1: &AtomicU32, _val: u32, n_wake: usize,
+                          _f2: *const AtomicU32, n_requeue: usize) -> Option<usize> {
+    Some(futex_wake(f1, n_wake.saturating_add(n_requeue)))
+}
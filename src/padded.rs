@@ -0,0 +1,58 @@
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use monitor::Mutex;
+
+/// Value aligned to, and so alone on, its own cache line
+///
+/// Threads writing values which share a line contend for it as if for the same value, e.g. the
+/// locks of an array of `Mutex`es, which are 4 bytes each; padding each to its own line keeps
+/// one thread's lock from slowing another's. The line is taken to be 128 bytes where the
+/// processor fetches lines in pairs, as on x86-64 and AArch64, else 64.
+#[cfg_attr(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "powerpc64"),
+           repr(align(128)))]
+#[cfg_attr(not(any(target_arch = "x86_64", target_arch = "aarch64",
+                   target_arch = "powerpc64")),
+           repr(align(64)))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CachePadded<T>(T);
+
+impl<T> CachePadded<T> {
+    #[inline] pub const fn new(x: T) -> Self { CachePadded(x) }
+
+    #[inline] pub fn into_inner(self) -> T { self.0 }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { &self.0 }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { &mut self.0 }
+}
+
+impl<T> From<T> for CachePadded<T> {
+    #[inline] fn from(x: T) -> Self { CachePadded(x) }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CachePadded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CachePadded").field(&self.0).finish()
+    }
+}
+
+/// `Mutex` alone on its own cache line, for arrays of locks, such as a table of shards
+///
+/// ```
+/// # extern crate user_sync;
+/// use user_sync::{CachePadded, Mutex, PaddedMutex};
+///
+/// # fn main() {
+/// let shards: [PaddedMutex<u32>; 2] = [CachePadded::new(Mutex::new(0)),
+///                                      CachePadded::new(Mutex::new(0))];
+/// *shards[1].lock() += 1;
+/// assert_eq!(1, *shards[1].lock());
+/// # }
+/// ```
+pub type PaddedMutex<T> = CachePadded<Mutex<T>>;
@@ -0,0 +1,64 @@
+use core::ops::Range;
+use std::any::Any;
+use std::boxed::Box;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+
+use lazy_barrier::LazyBarrier;
+use monitor::Mutex;
+
+/// Call `f` on each index in `range`, from `n_threads` new threads, each taking a contiguous
+/// part of the range, and return once all calls have returned.
+///
+/// The threads and the caller meet at a `LazyBarrier` when done. If any call panics, its thread
+/// still reaches the barrier, and the caller then panics with the first such payload. Should a
+/// thread fail to spawn, the caller makes the calls of that part and the rest itself, and the
+/// barrier is started for the threads which did spawn.
+///
+/// ```
+/// # extern crate user_sync;
+/// # use std::sync::Arc;
+/// use user_sync::{Mutex, parallel_for};
+///
+/// # fn main() {
+/// let sum = Arc::new(Mutex::new(0));
+/// let sum_ = sum.clone();
+/// parallel_for(0..100, 4, move |i| *sum_.lock() += i);
+/// assert_eq!(4950, *sum.lock());
+/// # }
+/// ```
+pub fn parallel_for<F: Fn(usize) + Send + Sync + 'static>(range: Range<usize>, n_threads: usize,
+                                                          f: F) {
+    let n_threads = n_threads.max(1);
+    let len = range.end.saturating_sub(range.start);
+    let chunk = len.div_ceil(n_threads);
+    let part = |k: usize| range.start + k.saturating_mul(chunk).min(len);
+    let shared = Arc::new((f, LazyBarrier::new(), Mutex::new(None::<Box<dyn Any + Send>>)));
+    let mut n_spawned = 0;
+    while n_spawned < n_threads {
+        let shared_ = shared.clone();
+        let (start, end) = (part(n_spawned), part(n_spawned + 1));
+        let spawned = thread::Builder::new().spawn(move || {
+            let (ref f, ref barrier, ref panicked) = *shared_;
+            run(f, panicked, start..end);
+            barrier.wait();
+        });
+        if spawned.is_err() { break };
+        n_spawned += 1;
+    }
+    let (ref f, ref barrier, ref panicked) = *shared;
+    if n_spawned < n_threads { run(f, panicked, part(n_spawned)..part(n_threads)) }
+    barrier.start(n_spawned as u32 + 1);
+    barrier.wait();
+    let e = panicked.lock().take();
+    if let Some(e) = e { panic::resume_unwind(e) }
+}
+
+// Call `f` on each index in `range`, keeping the first payload of a panic in `panicked`.
+fn run<F: Fn(usize)>(f: &F, panicked: &Mutex<Option<Box<dyn Any + Send>>>, range: Range<usize>) {
+    let run = AssertUnwindSafe(|| for i in range { f(i) });
+    if let Err(e) = panic::catch_unwind(run) {
+        panicked.lock().get_or_insert(e);
+    }
+}
@@ -0,0 +1,126 @@
+use core::hint::spin_loop as cpu_relax;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+
+// state: phase in the high half; in the low half, RELEASING while the last party to arrive
+// releases the rest, whereupon others wait for it to be reset, the number of parties, and the
+// number of them arrived.
+// seq: phase, stored before the state moves to the next phase, as in `Barrier`.
+const RELEASING: u64 = 1 << 31;
+const PARTIES_SHIFT: u32 = 16;
+const COUNT_MASK: u64 = 0xFFFF;
+const MAX_PARTIES: u32 = 0x7FFF;
+
+/// Barrier whose parties may register and deregister as it goes
+///
+/// Each phase ends once every party registered arrived, whereupon the next begins. A party
+/// registered during a phase must arrive in it too; one which deregisters counts as arrived.
+/// At most 0x7FFF parties may be registered at once.
+#[derive(Debug)]
+pub struct Phaser {
+    state: AtomicU64,
+    seq: AtomicU32,
+}
+
+impl Phaser {
+    /// Make a phaser with `n` parties registered.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if `n` is more than 0x7FFF.
+    #[inline] pub fn new(n: u32) -> Self {
+        assert!(n <= MAX_PARTIES, "Phaser made with too many parties");
+        Phaser { state: AtomicU64::new((n as u64) << PARTIES_SHIFT), seq: AtomicU32::new(0) }
+    }
+
+    /// Register another party; return the phase it joined.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if 0x7FFF parties are registered already.
+    pub fn register(&self) -> u32 {
+        let mut state = self.state.load(Memord::Acquire);
+        loop {
+            if 0 != state & RELEASING {
+                cpu_relax();
+                state = self.state.load(Memord::Acquire);
+                continue;
+            }
+            assert!(parties(state) < MAX_PARTIES, "Phaser registered with too many parties");
+            let new = state + (1 << PARTIES_SHIFT);
+            match self.state.compare_exchange_weak(state, new, Memord::AcqRel, Memord::Acquire) {
+                Ok(_) => return (state >> 32) as u32,
+                Err(old) => state = old,
+            }
+        }
+    }
+
+    /// Arrive without waiting for the rest; return the phase arrived in.
+    #[inline] pub fn arrive(&self) -> u32 { self.advance(false) }
+
+    /// Arrive and wait for the rest; return the phase arrived in, which has then ended.
+    #[inline] pub fn arrive_and_wait(&self) -> u32 {
+        let phase = self.advance(false);
+        while self.seq.load(Memord::Acquire) == phase { futex_wait(&self.seq, phase) }
+        phase
+    }
+
+    /// Arrive and deregister, without waiting for the rest; return the phase arrived in.
+    ///
+    /// If the last party deregisters, the phase ends, and the next has no parties until one
+    /// registers.
+    #[inline] pub fn arrive_and_deregister(&self) -> u32 { self.advance(true) }
+
+    // Count the caller as arrived, or, if it deregisters, no longer as a party; if then all
+    // parties arrived, release them to the next phase. Return the phase arrived in.
+    fn advance(&self, deregister: bool) -> u32 {
+        let mut state = self.state.load(Memord::Acquire);
+        loop {
+            if 0 != state & RELEASING {
+                cpu_relax();
+                state = self.state.load(Memord::Acquire);
+                continue;
+            }
+            let (phase, n, arrived) = ((state >> 32) as u32, parties(state), arrived(state));
+            assert!(arrived < n, "Phaser arrived at by more than its parties");
+            let (n, arrived) = if deregister { (n - 1, arrived) } else { (n, arrived + 1) };
+            let last = arrived == n;
+            let new = (phase as u64) << 32 | (n as u64) << PARTIES_SHIFT | arrived as u64 |
+                      if last { RELEASING } else { 0 };
+            if let Err(old) = self.state.compare_exchange_weak(state, new, Memord::AcqRel,
+                                                               Memord::Acquire) {
+                state = old;
+                continue;
+            }
+            if last {
+                let next = phase.wrapping_add(1);
+                self.seq.store(next, Memord::Release);
+                self.state.store((next as u64) << 32 | (n as u64) << PARTIES_SHIFT,
+                                 Memord::Release);
+                futex_wake(&self.seq, !0);
+            }
+            return phase;
+        }
+    }
+
+    /// Current phase, which advances by one, modulo 2^32, each time all parties arrive
+    #[inline] pub fn phase(&self) -> u32 { self.seq.load(Memord::Acquire) }
+
+    /// Number of parties registered
+    ///
+    /// Parties may register or deregister meanwhile, so this is only advisory.
+    #[inline] pub fn parties(&self) -> u32 { parties(self.state.load(Memord::Relaxed)) }
+
+    /// Number of parties arrived in the current phase
+    ///
+    /// Parties may arrive meanwhile, so this is only advisory.
+    #[inline] pub fn arrived(&self) -> u32 {
+        let state = self.state.load(Memord::Relaxed);
+        if 0 != state & RELEASING { 0 } else { arrived(state) }
+    }
+}
+
+#[inline] fn parties(state: u64) -> u32 { ((state >> PARTIES_SHIFT) & (COUNT_MASK >> 1)) as u32 }
+
+#[inline] fn arrived(state: u64) -> u32 { (state & COUNT_MASK) as u32 }
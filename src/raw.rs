@@ -0,0 +1,73 @@
+//! Raw futex operations
+//!
+//! These are the primitives the rest of the crate is built on, for building further ones.
+//! They do no bookkeeping: a futex word is any `AtomicU32`, and it is the caller's protocol
+//! which gives meaning to its values.
+//!
+//! Example: release a lock word and clear a flag word in one system call, waking a waiter of
+//! the lock, and all waiters of the flag if it had been set:
+//!
+//! ```
+//! # extern crate user_sync;
+//! # use std::sync::atomic::{AtomicU32, Ordering};
+//! use user_sync::raw::*;
+//!
+//! # fn main() {
+//! let lock = AtomicU32::new(2);
+//! let flag = AtomicU32::new(1);
+//!
+//! lock.store(0, Ordering::Release);
+//! futex_wake_op(&lock, 1, &flag, !0, futex_op(FUTEX_OP_SET, 0, FUTEX_OP_CMP_NE, 0));
+//! assert_eq!(0, flag.load(Ordering::Relaxed));
+//! # }
+//! ```
+
+use core::sync::atomic::{Ordering as Memord, fence};
+
+pub use linux::{futex_wait, futex_wait_timeout, futex_wake, futex_wake_op, futex_cmp_requeue,
+                futex_op,
+                FUTEX_OP_SET, FUTEX_OP_ADD, FUTEX_OP_OR, FUTEX_OP_ANDN, FUTEX_OP_XOR,
+                FUTEX_OP_OPARG_SHIFT,
+                FUTEX_OP_CMP_EQ, FUTEX_OP_CMP_NE, FUTEX_OP_CMP_LT, FUTEX_OP_CMP_LE,
+                FUTEX_OP_CMP_GT, FUTEX_OP_CMP_GE};
+
+/// Order all writes before this before any atomic write after it, as seen by a thread which
+/// observes the latter and then calls `consume`; this is a release fence.
+///
+/// Example: hand data to a thread blocked on a futex word:
+///
+/// ```
+/// # extern crate user_sync;
+/// # use std::sync::atomic::{AtomicU32, Ordering};
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// use user_sync::raw::*;
+///
+/// # fn main() {
+/// let shared = Arc::new((AtomicU32::new(0), AtomicU32::new(0)));
+/// let consumer = {
+///     let shared = shared.clone();
+///     thread::spawn(move || {
+///         let (ref data, ref flag) = *shared;
+///         while 0 == flag.load(Ordering::Relaxed) { futex_wait(flag, 0) }
+///         consume();
+///         assert_eq!(42, data.load(Ordering::Relaxed));
+///     })
+/// };
+///
+/// let (ref data, ref flag) = *shared;
+/// data.store(42, Ordering::Relaxed);
+/// publish();
+/// flag.store(1, Ordering::Relaxed);
+/// futex_wake(flag, 1);
+/// # consumer.join().unwrap();
+/// # }
+/// ```
+#[inline] pub fn publish() { fence(Memord::Release) }
+
+/// Order any atomic read before this, which observes a write after a `publish` call, before all
+/// reads after it, so they see all writes before that `publish` call; this is an acquire fence.
+///
+/// Despite the name, it is not `memory_order_consume`, which is weaker.
+/// See `publish` for an example.
+#[inline] pub fn consume() { fence(Memord::Acquire) }
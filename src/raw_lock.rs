@@ -0,0 +1,82 @@
+//! Backends for the generic lock types of `lock_api`
+//!
+//! `RawMutex` and `RawRwLock` are the futex locks beneath `Mutex` and `RwLock`, bare, to plug
+//! into `lock_api::Mutex` and `lock_api::RwLock`, or whatever else is generic over its traits.
+//!
+//! ```
+//! # extern crate lock_api;
+//! # extern crate user_sync;
+//! type Mutex<T> = lock_api::Mutex<user_sync::RawMutex, T>;
+//!
+//! # fn main() {
+//! let count = Mutex::new(0);
+//! *count.lock() += 1;
+//! assert_eq!(1, *count.lock());
+//! # }
+//! ```
+
+use core::time::Duration;
+use lock_api::{self, GuardNoSend};
+
+use time::Instant;
+
+/// Futex lock which implements `lock_api::RawMutex`, and its `Fair` and `Timed` extensions
+#[derive(Debug)]
+pub struct RawMutex(::system::Mutex);
+
+unsafe impl lock_api::RawMutex for RawMutex {
+    const INIT: Self = RawMutex(::system::Mutex::new());
+
+    // The owner is tracked in debug builds, so a guard sent to and dropped in another thread
+    // would trip an assertion.
+    type GuardMarker = GuardNoSend;
+
+    #[inline] fn lock(&self) { self.0.lock(::default_policy()) }
+
+    #[inline] fn try_lock(&self) -> bool { self.0.try_lock() }
+
+    #[inline] unsafe fn unlock(&self) { self.0.unlock(::default_policy()) }
+
+    #[inline] fn is_locked(&self) -> bool { self.0.is_locked() }
+}
+
+unsafe impl lock_api::RawMutexFair for RawMutex {
+    #[inline] unsafe fn unlock_fair(&self) { self.0.unlock_fair() }
+
+    #[inline] unsafe fn bump(&self) { self.0.bump(::default_policy()) }
+}
+
+unsafe impl lock_api::RawMutexTimed for RawMutex {
+    type Duration = Duration;
+    type Instant = Instant;
+
+    #[inline] fn try_lock_for(&self, timeout: Duration) -> bool {
+        self.try_lock_until(Instant::now() + timeout)
+    }
+
+    #[inline] fn try_lock_until(&self, deadline: Instant) -> bool {
+        self.0.lock_until(::default_policy(), deadline.0)
+    }
+}
+
+/// Futex reader-writer lock which implements `lock_api::RawRwLock`
+#[derive(Debug)]
+pub struct RawRwLock(::system::RwLock);
+
+unsafe impl lock_api::RawRwLock for RawRwLock {
+    const INIT: Self = RawRwLock(::system::RwLock::new());
+
+    type GuardMarker = GuardNoSend;
+
+    #[inline] fn lock_shared(&self) { self.0.read(::default_policy().spins) }
+
+    #[inline] fn try_lock_shared(&self) -> bool { self.0.try_read() }
+
+    #[inline] unsafe fn unlock_shared(&self) { self.0.read_unlock() }
+
+    #[inline] fn lock_exclusive(&self) { self.0.write(::default_policy().spins) }
+
+    #[inline] fn try_lock_exclusive(&self) -> bool { self.0.try_write() }
+
+    #[inline] unsafe fn unlock_exclusive(&self) { self.0.write_unlock(::default_policy().spins) }
+}
@@ -0,0 +1,115 @@
+//! Read-copy-update
+//!
+//! Readers count themselves in one of two slots, chosen by the epoch, and out when done; a
+//! writer, having swapped in the new value, waits for each slot in turn to drain, flipping the
+//! epoch before each so that readers arriving meanwhile count themselves in the other. A reader
+//! which may see the old value entered its slot before the swap, so once both slots have been
+//! seen empty since, none can, and the old value may be freed. The writer flags that it waits,
+//! so the reader which empties a slot knows to wake it. The counts are on a cache line of their
+//! own, so readers counting themselves in and out don't stall others loading the pointer.
+
+use alloc::boxed::Box;
+use core::fmt;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering as Memord};
+
+use padded::CachePadded;
+use system::{futex_wait, futex_wake};
+
+/// Value which is read wait-free and replaced whole
+///
+/// Reads never block: `read` counts the reader in, and the guard counts it out, waking the
+/// writer if it was the last reader the writer waits for. `replace` swaps in a new value at
+/// once, so readers from then on see it, but then blocks for a grace period, until every reader
+/// which may see the old value is done, before it returns the old value. Writers are serialized
+/// by an internal mutex.
+///
+/// This suits values read often and replaced rarely, such as configuration.
+///
+/// Calling `replace` while holding a guard of the same `Rcu` in the same thread never returns.
+pub struct Rcu<T> {
+    ptr: AtomicPtr<T>,
+    epoch: AtomicUsize,
+    readers: CachePadded<[AtomicU32; 2]>,
+    waiting: AtomicU32,
+    writer: ::system::Mutex,
+}
+
+unsafe impl<T: Send + Sync> Send for Rcu<T> {}
+unsafe impl<T: Send + Sync> Sync for Rcu<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for Rcu<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Rcu").field("valu", &*self.read()).finish()
+    }
+}
+
+impl<T> Rcu<T> {
+    #[inline] pub fn new(x: T) -> Self {
+        Rcu {
+            ptr: AtomicPtr::new(Box::into_raw(Box::new(x))),
+            epoch: AtomicUsize::new(0),
+            readers: CachePadded::new([AtomicU32::new(0), AtomicU32::new(0)]),
+            waiting: AtomicU32::new(0),
+            writer: ::system::Mutex::new(),
+        }
+    }
+
+    /// Take a shared reference to the value, which stays valid, even if the value is replaced
+    /// meanwhile, until the guard is dropped.
+    #[inline] pub fn read(&self) -> RcuGuard<'_, T> {
+        let slot = self.epoch.load(Memord::SeqCst) & 1;
+        self.readers[slot].fetch_add(1, Memord::SeqCst);
+        let valu = unsafe { &*self.ptr.load(Memord::SeqCst) };
+        RcuGuard { rcu: self, slot, valu }
+    }
+
+    /// Replace the value, and return the old value once no reader can see it.
+    pub fn replace(&self, x: T) -> T {
+        self.writer.lock(::default_policy());
+        let old = self.ptr.swap(Box::into_raw(Box::new(x)), Memord::SeqCst);
+        self.waiting.store(1, Memord::SeqCst);
+        for _ in 0..2 {
+            let slot = self.epoch.fetch_add(1, Memord::SeqCst) & 1;
+            loop {
+                match self.readers[slot].load(Memord::SeqCst) {
+                    0 => break,
+                    n => futex_wait(&self.readers[slot], n),
+                }
+            }
+        }
+        self.waiting.store(0, Memord::Relaxed);
+        self.writer.unlock(::default_policy());
+        *unsafe { Box::from_raw(old) }
+    }
+
+    /// Take an exclusive reference to the value; this being `&mut self`, no reader can hold it.
+    #[inline] pub fn get_mut(&mut self) -> &mut T { unsafe { &mut **self.ptr.get_mut() } }
+}
+
+impl<T> Drop for Rcu<T> {
+    #[inline] fn drop(&mut self) { unsafe { drop(Box::from_raw(*self.ptr.get_mut())) } }
+}
+
+/// Shared reference to `Rcu`-guarded value
+///
+/// A writer replacing the value waits until this is dropped to return the old value.
+#[derive(Debug)]
+pub struct RcuGuard<'a, T: 'a> {
+    rcu: &'a Rcu<T>,
+    slot: usize,
+    valu: &'a T,
+}
+
+impl<'a, T> Deref for RcuGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { self.valu }
+}
+
+impl<'a, T> Drop for RcuGuard<'a, T> {
+    #[inline] fn drop(&mut self) {
+        let readers = &self.rcu.readers[self.slot];
+        if 1 == readers.fetch_sub(1, Memord::SeqCst) &&
+           0 != self.rcu.waiting.load(Memord::SeqCst) { futex_wake(readers, 1); }
+    }
+}
@@ -0,0 +1,158 @@
+//! Redox system calls
+//!
+//! Redox's `futex` call waits and wakes as Linux's, so the futex backend runs on it as it is;
+//! but it can't compare the word as it moves waiters, nor tells how many it moved, nor modify a
+//! word as it wakes. The kernel keys each futex by its physical address, so all are shared
+//! between processes which map them.
+
+use core::ptr;
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+use core::time::Duration;
+
+use redox_syscall::{self as sys, TimeSpec};
+use redox_syscall::error::{EAGAIN, EINTR, ETIMEDOUT};
+use redox_syscall::flag::{CLOCK_MONOTONIC, FUTEX_WAIT, FUTEX_WAKE};
+
+// The call takes counts as `int`.
+#[inline] fn count(n: usize) -> i32 { n.min(i32::MAX as usize) as i32 }
+
+#[inline] fn addr(f: &AtomicU32) -> *mut i32 { f as *const _ as *mut i32 }
+
+// Block while `*f == val`, for at most `timeout`, or until woken if `None`; return `false` if it
+// timed out. A signal makes a spurious return, as on Linux.
+#[inline] fn wait(f: &AtomicU32, val: u32, timeout: Option<u64>) -> Result<bool, i32> {
+    let ts = timeout.map(|t| TimeSpec { tv_sec: (t / 1_000_000_000) as i64,
+                                        tv_nsec: (t % 1_000_000_000) as i32 });
+    let ts = ts.as_ref().map_or(ptr::null(), |ts| ts as *const TimeSpec);
+    match unsafe { sys::futex(addr(f), FUTEX_WAIT, val as i32, ts as usize, ptr::null_mut()) } {
+        Ok(_) => Ok(true),
+        Err(e) if ETIMEDOUT == e.errno => Ok(false),
+        Err(e) if EAGAIN == e.errno || EINTR == e.errno => Ok(true),
+        Err(e) => Err(e.errno),
+    }
+}
+
+/// Identifier of the calling thread
+///
+/// Redox numbers threads as processes, so this numbers them itself, from 1 as each first asks.
+#[cfg(feature = "std")]
+#[inline]
+pub fn gettid() -> u32 {
+    use core::cell::Cell;
+
+    static NEXT: AtomicU32 = AtomicU32::new(1);
+    thread_local!(static TID: Cell<u32> = const { Cell::new(0) });
+    TID.with(|tid| {
+        if 0 == tid.get() { tid.set(NEXT.fetch_add(1, Memord::Relaxed)) };
+        tid.get()
+    })
+}
+
+/// Identifier of the calling thread
+///
+/// Without `std`, and so without a thread-local to number it, that of its process, as Redox
+/// numbers threads as processes.
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn gettid() -> u32 { sys::getpid().unwrap_or(0) as u32 }
+
+/// Let another thread run.
+#[inline]
+pub fn yield_now() { let _ = sys::sched_yield(); }
+
+/// Nanoseconds on the monotonic clock
+#[inline]
+pub fn monotonic_now() -> u64 {
+    let mut ts = TimeSpec { tv_sec: 0, tv_nsec: 0 };
+    let _ = sys::clock_gettime(CLOCK_MONOTONIC, &mut ts);
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Block while `*f == val`.
+///
+/// As on Linux, may return spuriously, so the caller must recheck its condition.
+#[inline]
+pub fn futex_wait(f: &AtomicU32, val: u32) {
+    let r = wait(f, val, None);
+    debug_assert!(r.is_ok(), "futex wait failed: error {:?}", r);
+}
+
+/// Block while `*f == val`, for at most `timeout`; return `false` if it timed out.
+#[inline]
+pub fn futex_wait_timeout(f: &AtomicU32, val: u32, timeout: Duration) -> Result<bool, i32> {
+    let nanos = timeout.as_secs().saturating_mul(1_000_000_000)
+                       .saturating_add(timeout.subsec_nanos() as u64);
+    futex_wait_until(f, val, monotonic_now().saturating_add(nanos))
+}
+
+/// As `futex_wait`, but return `false` if a signal interrupted it.
+#[inline]
+pub fn futex_wait_interruptible(f: &AtomicU32, val: u32) -> bool {
+    match unsafe { sys::futex(addr(f), FUTEX_WAIT, val as i32, 0, ptr::null_mut()) } {
+        Ok(_) => true,
+        Err(e) if EINTR == e.errno => false,
+        Err(e) => { debug_assert_eq!(EAGAIN, e.errno, "futex wait failed"); true },
+    }
+}
+
+/// Block while `*f == val`, until `deadline`, in nanoseconds on the monotonic clock.
+///
+/// Returns `Ok(false)` if the deadline passed, else as `futex_wait`. The call takes a timeout
+/// rather than a deadline, so this waits again for what remains of it after a spurious return.
+#[inline]
+pub fn futex_wait_until(f: &AtomicU32, val: u32, deadline: u64) -> Result<bool, i32> {
+    loop {
+        let now = monotonic_now();
+        if now >= deadline { return Ok(false) };
+        if wait(f, val, Some(deadline - now))? { return Ok(true) };
+    }
+}
+
+// A timed wait of the crate's own primitives which failed would fail again if retried, and is
+// no timeout, so panic.
+#[cold]
+pub(crate) fn wait_failed(e: i32) -> ! { panic!("futex wait failed: error {}", e) }
+
+// Every futex is shared, so the shared waits and wakes are the others.
+#[inline]
+pub(crate) fn futex_wait_shared(f: &AtomicU32, val: u32) { futex_wait(f, val) }
+
+#[inline]
+pub(crate) fn futex_wait_until_shared(f: &AtomicU32, val: u32,
+                                      deadline: u64) -> Result<bool, i32> {
+    futex_wait_until(f, val, deadline)
+}
+
+#[inline]
+pub(crate) fn futex_wake_shared(f: &AtomicU32, n: usize) -> usize { futex_wake(f, n) }
+
+/// Unblock at most `n` threads waiting on `f`; return how many were unblocked.
+#[inline]
+pub fn futex_wake(f: &AtomicU32, n: usize) -> usize {
+    unsafe { sys::futex(addr(f), FUTEX_WAKE, count(n), 0, ptr::null_mut()) }.unwrap_or(0)
+}
+
+// Store `val` in `f`, and unblock a thread waiting on it; the wake only finds the waiter by the
+// address of `f`, so it needn't be valid by then.
+#[inline]
+pub(crate) fn futex_store_wake(f: &AtomicU32, val: u32) {
+    let p = addr(f);
+    f.store(val, Memord::Release);
+    let _ = unsafe { sys::futex(p, FUTEX_WAKE, 1, 0, ptr::null_mut()) };
+}
+
+// Unblock a thread waiting on each of `f1` and `f2`; return how many were unblocked.
+#[inline]
+pub(crate) fn futex_wake_both(f1: &AtomicU32, f2: &AtomicU32) -> usize {
+    futex_wake(f1, 1) + futex_wake(f2, 1)
+}
+
+// The kernel's requeue tells only how many threads it woke, not whether it moved any, whereupon
+// the caller must mark `f2` contended, so unblock the threads rather than move them; they then
+// block on `f2` as they take it, if they must. As none is moved, it needn't compare `*f1` to
+// `val`.
+#[inline]
+pub(crate) fn cmp_requeue(f1: &AtomicU32, _val: u32, n_wake: usize,
+                          _f2: *const AtomicU32, n_requeue: usize) -> Option<usize> {
+    Some(futex_wake(f1, n_wake.saturating_add(n_requeue)))
+}
@@ -0,0 +1,56 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+/// Mutex which the thread holding it may lock again
+///
+/// It is released when every guard is dropped. As the guards of one thread may coexist, each
+/// only gives a shared reference; use a `Cell` or `RefCell` within to mutate the value.
+#[derive(Debug)]
+pub struct ReentrantMutex<T: ?Sized> {
+    lock: ::system::ReentrantMutex,
+    valu: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for ReentrantMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for ReentrantMutex<T> {}
+
+impl<T> ReentrantMutex<T> {
+    #[inline] pub const fn new(x: T) -> Self {
+        ReentrantMutex { lock: ::system::ReentrantMutex::new(), valu: UnsafeCell::new(x) }
+    }
+}
+
+impl<T: ?Sized> ReentrantMutex<T> {
+    /// Take a reference to the guarded value, blocking if another thread is holding it.
+    #[inline] pub fn lock(&self) -> ReentrantGuard<'_, T> {
+        self.lock.lock(::default_policy().spins);
+        ReentrantGuard { lock: &self.lock, valu: unsafe { &*self.valu.get() },
+                         unsend: PhantomData }
+    }
+
+    /// Take a reference to the guarded value, returning `None` if another thread is holding it.
+    #[inline] pub fn try_lock(&self) -> Option<ReentrantGuard<'_, T>> {
+        if self.lock.try_lock() {
+            Some(ReentrantGuard { lock: &self.lock, valu: unsafe { &*self.valu.get() },
+                                  unsend: PhantomData })
+        } else { None }
+    }
+}
+
+/// Reference to `ReentrantMutex`-guarded value
+#[derive(Debug)]
+pub struct ReentrantGuard<'a, T: ?Sized + 'a> {
+    lock: &'a ::system::ReentrantMutex,
+    valu: &'a T,
+    unsend: PhantomData<*mut ()>,
+}
+
+impl<'a, T: ?Sized> Deref for ReentrantGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { self.valu }
+}
+
+impl<'a, T: ?Sized> Drop for ReentrantGuard<'a, T> {
+    #[inline] fn drop(&mut self) { self.lock.unlock(::default_policy().spins) }
+}
@@ -0,0 +1,37 @@
+//! Registry of named locks
+//!
+//! A lock made by `Mutex::new_named` is added here by `Mutex::register`, which takes it by
+//! `'static` reference, so a listed lock is never moved nor dropped, and may be read at any time.
+
+use std::sync::Mutex as StdMutex;
+use std::vec::Vec;
+
+use system::Mutex;
+
+struct Entry {
+    name: &'static str,
+    lock: &'static Mutex,
+}
+
+static LOCKS: StdMutex<Vec<Entry>> = StdMutex::new(Vec::new());
+
+pub fn register(name: &'static str, lock: &'static Mutex) {
+    LOCKS.lock().unwrap_or_else(|e| e.into_inner()).push(Entry { name, lock });
+}
+
+/// Print each named lock to standard error, with the address of its futex word, whether it is
+/// held and contended, and its owner if known.
+pub fn dump_locks() {
+    let locks = LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+    for e in locks.iter() {
+        let lock = e.lock;
+        let state = match lock.state() {
+            0 => "unlocked", 1 => "locked", 2 => "contended", _ => "handed off",
+        };
+        match lock.owner() {
+            0 => eprintln!("{} @ {:p}: {}", e.name, lock.futex_addr(), state),
+            tid => eprintln!("{} @ {:p}: {}, held by thread {}", e.name, lock.futex_addr(),
+                             state, tid),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use thread::ThreadId;
+
+/// Mutex which survives the death of its owner
+///
+/// If a thread exits holding it, or its process dies, the kernel releases it, and the next
+/// thread to take it learns so by `OwnerDied`, whereupon it holds the lock and may restore the
+/// guarded value to a consistent state. As the kernel may wake waiters from another process, it
+/// is safe in memory shared between processes if `T` is.
+///
+/// The kernel learns which locks a thread holds from a list registered by the thread, once, at
+/// its first robust lock; this replaces any such list registered by the C library, so this may
+/// not be used in a thread which also uses robust `pthread` mutexen, which is why it is only
+/// built with the `robust` feature. The child of a fork registers its own list afresh.
+///
+/// # Panics
+///
+/// Dropping a `RobustMutex` held by another thread, e.g. as its guard was forgotten, panicks,
+/// lest the kernel write into memory freed by then when the holder exits.
+///
+/// Taking the lock panicks if the kernel refuses the thread's list, e.g. under an emulator or
+/// sandbox which lacks or forbids `set_robust_list`, as the lock would then not survive the
+/// death of its owner.
+#[derive(Debug)]
+pub struct RobustMutex<T: ?Sized> {
+    lock: ::system::RobustMutex,
+    valu: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RobustMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for RobustMutex<T> {}
+
+/// Result of taking a lock whose last owner may have died holding it
+pub type LockResult<G> = Result<G, OwnerDied<G>>;
+
+/// Error of taking a lock whose last owner died holding it, holding the guard nonetheless
+#[derive(Debug)]
+pub struct OwnerDied<G>(pub G);
+
+impl<T> RobustMutex<T> {
+    /// # Safety
+    ///
+    /// The mutex must not be moved while locked, as it may be once a guard of it is forgotten:
+    /// the holder's robust list links to it where it is, and the kernel follows the link as the
+    /// holder dies, and the holder as it unlocks it. A `static`, or a mutex in memory mapped
+    /// for good, never moves.
+    #[inline] pub const unsafe fn new(x: T) -> Self {
+        RobustMutex { lock: ::system::RobustMutex::new(), valu: UnsafeCell::new(x) }
+    }
+}
+
+impl<T: ?Sized> RobustMutex<T> {
+    /// Take an exclusive reference to the guarded value, blocking if another thread is already
+    /// holding it.
+    #[inline] pub fn lock(&self) -> LockResult<RobustGuard<'_, T>> {
+        let lived = self.lock.lock(::default_policy().spins).unwrap_or_else(|e| refused(e));
+        let guard = RobustGuard { lock: &self.lock, valu: unsafe { &mut *self.valu.get() },
+                                  unsend: PhantomData };
+        if lived { Ok(guard) } else { Err(OwnerDied(guard)) }
+    }
+
+    /// Take an exclusive reference to the guarded value, returning `None` if another thread is
+    /// already holding it.
+    #[inline] pub fn try_lock(&self) -> Option<LockResult<RobustGuard<'_, T>>> {
+        self.lock.try_lock().unwrap_or_else(|e| refused(e)).map(|lived| {
+            let guard = RobustGuard { lock: &self.lock, valu: unsafe { &mut *self.valu.get() },
+                                      unsend: PhantomData };
+            if lived { Ok(guard) } else { Err(OwnerDied(guard)) }
+        })
+    }
+
+    /// Thread holding the lock, if any
+    #[inline] pub fn owner(&self) -> Option<ThreadId> {
+        match self.lock.owner() { 0 => None, tid => Some(ThreadId(tid)) }
+    }
+}
+
+#[cold]
+fn refused(e: ::futex::FutexError) -> ! { panic!("robust list refused: {}", e) }
+
+/// Exclusive reference to `RobustMutex`-guarded value
+#[derive(Debug)]
+pub struct RobustGuard<'a, T: ?Sized + 'a> {
+    lock: &'a ::system::RobustMutex,
+    valu: &'a mut T,
+    unsend: PhantomData<*mut ()>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync> Sync for RobustGuard<'a, T> {}
+
+impl<'a, T: ?Sized> Deref for RobustGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { self.valu }
+}
+
+impl<'a, T: ?Sized> DerefMut for RobustGuard<'a, T> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { self.valu }
+}
+
+impl<'a, T: ?Sized> Drop for RobustGuard<'a, T> {
+    #[inline] fn drop(&mut self) { self.lock.unlock() }
+}
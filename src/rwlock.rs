@@ -0,0 +1,104 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/// Reader-writer lock
+///
+/// Any number of threads may hold it for reading, or one for writing. A writer waiting for
+/// readers to leave bars more from entering, so readers can't starve writers.
+///
+/// It is not recursive: a thread which holds the lock for reading and reads again may wait
+/// forever, for a writer which came between waits for the first read to end. In debug builds,
+/// a thread which holds the lock and asks for it again, for reading or writing, even by
+/// `try_read` or `try_write`, panics rather than wait on itself.
+#[derive(Debug)]
+pub struct RwLock<T: ?Sized> {
+    lock: ::system::RwLock,
+    valu: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    #[inline] pub const fn new(x: T) -> Self {
+        RwLock { lock: ::system::RwLock::new(), valu: UnsafeCell::new(x) }
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Take a shared reference to the guarded value, blocking while a writer holds or awaits
+    /// the lock.
+    #[inline] pub fn read(&self) -> ReadGuard<'_, T> {
+        self.lock.read(::default_policy().spins);
+        ReadGuard { lock: &self.lock, valu: unsafe { &*self.valu.get() }, unsend: PhantomData }
+    }
+
+    /// Take a shared reference to the guarded value, returning `None` if a writer holds or
+    /// awaits the lock.
+    #[inline] pub fn try_read(&self) -> Option<ReadGuard<'_, T>> {
+        if self.lock.try_read() {
+            Some(ReadGuard { lock: &self.lock, valu: unsafe { &*self.valu.get() },
+                             unsend: PhantomData })
+        } else { None }
+    }
+
+    /// Take an exclusive reference to the guarded value, blocking while any other thread holds
+    /// the lock.
+    #[inline] pub fn write(&self) -> WriteGuard<'_, T> {
+        self.lock.write(::default_policy().spins);
+        WriteGuard { lock: &self.lock, valu: unsafe { &mut *self.valu.get() },
+                     unsend: PhantomData }
+    }
+
+    /// Take an exclusive reference to the guarded value, returning `None` if any other thread
+    /// holds the lock.
+    #[inline] pub fn try_write(&self) -> Option<WriteGuard<'_, T>> {
+        if self.lock.try_write() {
+            Some(WriteGuard { lock: &self.lock, valu: unsafe { &mut *self.valu.get() },
+                              unsend: PhantomData })
+        } else { None }
+    }
+}
+
+/// Shared reference to `RwLock`-guarded value
+#[derive(Debug)]
+pub struct ReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a ::system::RwLock,
+    valu: &'a T,
+    unsend: PhantomData<*mut ()>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync> Sync for ReadGuard<'a, T> {}
+
+impl<'a, T: ?Sized> Deref for ReadGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { self.valu }
+}
+
+impl<'a, T: ?Sized> Drop for ReadGuard<'a, T> {
+    #[inline] fn drop(&mut self) { self.lock.read_unlock() }
+}
+
+/// Exclusive reference to `RwLock`-guarded value
+#[derive(Debug)]
+pub struct WriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a ::system::RwLock,
+    valu: &'a mut T,
+    unsend: PhantomData<*mut ()>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync> Sync for WriteGuard<'a, T> {}
+
+impl<'a, T: ?Sized> Deref for WriteGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { self.valu }
+}
+
+impl<'a, T: ?Sized> DerefMut for WriteGuard<'a, T> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { self.valu }
+}
+
+impl<'a, T: ?Sized> Drop for WriteGuard<'a, T> {
+    #[inline] fn drop(&mut self) { self.lock.write_unlock(::default_policy().spins) }
+}
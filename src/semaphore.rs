@@ -0,0 +1,62 @@
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+use wait_any::Waitable;
+
+/// Counting semaphore
+///
+/// It holds a number of permits: `acquire` takes one, blocking until one is available, and
+/// `release` returns one. E.g. to bound how many threads do something at once, make it with
+/// that many permits, and have each acquire one before and release it after.
+#[derive(Debug)]
+pub struct Semaphore(AtomicU32);
+
+impl Semaphore {
+    /// Make a semaphore with `n` permits.
+    #[inline] pub const fn new(n: u32) -> Self { Semaphore(AtomicU32::new(n)) }
+
+    /// Take a permit, blocking until one is available.
+    #[inline] pub fn acquire(&self) {
+        let mut m = self.0.load(Memord::Relaxed);
+        loop {
+            if 0 == m {
+                futex_wait(&self.0, 0);
+                m = self.0.load(Memord::Relaxed);
+                continue;
+            }
+            match self.0.compare_exchange_weak(m, m - 1, Memord::Acquire, Memord::Relaxed) {
+                Ok(_) => return,
+                Err(n) => m = n,
+            }
+        }
+    }
+
+    /// Take a permit if one is available, else return `false`.
+    #[inline] pub fn try_acquire(&self) -> bool {
+        let mut m = self.0.load(Memord::Relaxed);
+        while 0 != m {
+            match self.0.compare_exchange_weak(m, m - 1, Memord::Acquire, Memord::Relaxed) {
+                Ok(_) => return true,
+                Err(n) => m = n,
+            }
+        }
+        false
+    }
+
+    /// Return a permit, unblocking a thread waiting for one.
+    #[inline] pub fn release(&self) {
+        self.0.fetch_add(1, Memord::Release);
+        futex_wake(&self.0, 1);
+    }
+
+    /// Number of permits available
+    ///
+    /// Other threads may acquire or release permits meanwhile, so this is only advisory.
+    #[inline] pub fn available(&self) -> u32 { self.0.load(Memord::Relaxed) }
+}
+
+impl Waitable for Semaphore {
+    #[inline] fn poll(&self) -> Result<(), (&AtomicU32, u32)> {
+        if self.try_acquire() { Ok(()) } else { Err((&self.0, 0)) }
+    }
+}
@@ -0,0 +1,99 @@
+use core::cell::UnsafeCell;
+use core::hint::spin_loop as cpu_relax;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering as Memord, fence};
+
+/// Sequence lock
+///
+/// Readers never block nor write shared memory: `read` copies the guarded value out and retries
+/// if a writer ran meanwhile, which it detects by an odd or changed sequence number.
+/// Writers are serialized by an internal mutex, so any number of threads may write; only writers
+/// contend on it.
+///
+/// `T: Copy` is required as a reader may copy out a value torn by a concurrent write, which it
+/// then discards; this is only sound if the copy has no side effects nor ownership.
+#[derive(Debug)]
+pub struct SeqLock<T: Copy> {
+    seq: AtomicUsize,
+    lock: ::system::Mutex,
+    valu: UnsafeCell<T>,
+}
+
+unsafe impl<T: Copy + Send> Send for SeqLock<T> {}
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+impl<T: Copy> SeqLock<T> {
+    #[inline] pub const fn new(x: T) -> Self {
+        SeqLock {
+            seq: AtomicUsize::new(0),
+            lock: ::system::Mutex::new(),
+            valu: UnsafeCell::new(x),
+        }
+    }
+
+    /// Copy out the guarded value, retrying until no write overlapped the copy.
+    #[inline] pub fn read(&self) -> T {
+        loop {
+            let seq = self.seq.load(Memord::Acquire);
+            if seq & 1 != 0 { cpu_relax(); continue };
+            let x = unsafe { ptr::read_volatile(self.valu.get()) };
+            fence(Memord::Acquire);
+            if self.seq.load(Memord::Relaxed) == seq { return x };
+            cpu_relax();
+        }
+    }
+
+    /// Take an exclusive reference to the guarded value, blocking if another thread is already
+    /// writing.
+    #[inline] pub fn write(&self) -> SeqLockGuard<'_, T> {
+        self.lock.lock(::default_policy());
+        let seq = self.seq.load(Memord::Relaxed);
+        debug_assert_eq!(0, seq & 1);
+        self.seq.store(seq.wrapping_add(1), Memord::Relaxed);
+        fence(Memord::Release);
+        SeqLockGuard { lock: self }
+    }
+}
+
+/// Exclusive reference to `SeqLock`-guarded value
+///
+/// Readers retry until this is dropped.
+#[derive(Debug)]
+pub struct SeqLockGuard<'a, T: Copy + 'a> {
+    lock: &'a SeqLock<T>,
+}
+
+impl<'a, T: Copy> Deref for SeqLockGuard<'a, T> {
+    type Target = T;
+    #[inline] fn deref(&self) -> &T { unsafe { &*self.lock.valu.get() } }
+}
+
+impl<'a, T: Copy> DerefMut for SeqLockGuard<'a, T> {
+    #[inline] fn deref_mut(&mut self) -> &mut T { unsafe { &mut *self.lock.valu.get() } }
+}
+
+impl<'a, T: Copy> Drop for SeqLockGuard<'a, T> {
+    #[inline] fn drop(&mut self) {
+        let seq = self.lock.seq.load(Memord::Relaxed);
+        self.lock.seq.store(seq.wrapping_add(1), Memord::Release);
+        self.lock.lock.unlock(::default_policy());
+    }
+}
+
+/// Value which is read and written whole, however large, without tearing
+///
+/// This is a `SeqLock` which is only ever read or written by copy: `load` never blocks, but
+/// retries while a `store` overlaps it, and stores are serialized by an internal mutex.
+/// It suits values read often and written rarely, such as configuration.
+#[derive(Debug)]
+pub struct AtomicSnapshot<T: Copy>(SeqLock<T>);
+
+impl<T: Copy> AtomicSnapshot<T> {
+    #[inline] pub const fn new(x: T) -> Self { AtomicSnapshot(SeqLock::new(x)) }
+
+    /// Copy out the value, as some `store` left it whole.
+    #[inline] pub fn load(&self) -> T { self.0.read() }
+
+    #[inline] pub fn store(&self, x: T) { *self.0.write() = x }
+}
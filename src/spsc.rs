@@ -0,0 +1,172 @@
+//! Single-producer single-consumer ring buffer
+//!
+//! A `Ring` over a buffer the caller provides, so it needs no allocator, is split into a
+//! `Producer` and a `Consumer`. Pushing and popping touch only the ring's indices, without a
+//! lock nor, while it is neither full nor empty, a system call; a producer finding it full, or
+//! a consumer finding it empty, blocks on a futex until the other makes room or pushes.
+//!
+//! ```
+//! # extern crate user_sync;
+//! use user_sync::spsc::Ring;
+//!
+//! # fn main() {
+//! let mut buf = [None; 4];
+//! let mut ring = Ring::new(&mut buf);
+//! let (mut tx, mut rx) = ring.split();
+//! tx.push(1);
+//! tx.push(2);
+//! assert_eq!(1, rx.pop());
+//! assert_eq!(Some(2), rx.try_pop());
+//! assert_eq!(None, rx.try_pop());
+//! # }
+//! ```
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use padded::CachePadded;
+use system::{futex_wait, futex_wake};
+
+const PRODUCER_WAITING: u32 = 1;
+const CONSUMER_WAITING: u32 = 2;
+
+// head: count of values popped; tail: count of values pushed; both modulo 2^32, so the number
+// in the ring is their difference, and that of a slot, the count modulo the capacity, which is
+// a power of two so as to divide 2^32, lest the slot jump as the count wraps.
+// waiting: who may be blocked, on the index the other moves; set before a side rechecks the
+// index and parks, and cleared by the other side as it wakes it.
+// Each index is on its own cache line, so the producer moving one doesn't stall the consumer
+// moving the other.
+/// Ring buffer for a single producer and a single consumer
+pub struct Ring<'a, T: 'a> {
+    slots: &'a [UnsafeCell<Option<T>>],
+    head: CachePadded<AtomicU32>,
+    tail: CachePadded<AtomicU32>,
+    waiting: AtomicU32,
+}
+
+unsafe impl<'a, T: Send> Sync for Ring<'a, T> {}
+
+impl<'a, T> fmt::Debug for Ring<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ring").field("capacity", &self.slots.len()).field("len", &self.len())
+         .finish()
+    }
+}
+
+impl<'a, T> Ring<'a, T> {
+    /// Make a ring over `buf`, which holds `buf.len()` values; any values in `buf` are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panicks unless the length of `buf` is a power of two, at most 2^31.
+    #[inline] pub fn new(buf: &'a mut [Option<T>]) -> Self {
+        assert!(buf.len().is_power_of_two() && buf.len() <= 1 << 31,
+                "Ring made over a bad buffer length");
+        for slot in buf.iter_mut() { *slot = None }
+        let slots = unsafe { &*(buf as *mut [Option<T>] as *const [UnsafeCell<Option<T>>]) };
+        Ring { slots, head: CachePadded::new(AtomicU32::new(0)),
+               tail: CachePadded::new(AtomicU32::new(0)), waiting: AtomicU32::new(0) }
+    }
+
+    /// Split into the producing and consuming halves.
+    #[inline] pub fn split(&mut self) -> (Producer<'_, T>, Consumer<'_, T>) {
+        let ring: &Ring<T> = self;
+        (Producer { ring }, Consumer { ring })
+    }
+
+    /// Number of values in the ring
+    ///
+    /// The producer and consumer may move meanwhile, so this is only advisory.
+    #[inline] pub fn len(&self) -> usize {
+        self.tail.load(Memord::Relaxed).wrapping_sub(self.head.load(Memord::Relaxed)) as usize
+    }
+
+    /// Whether the ring is empty; as `len`, only advisory
+    #[inline] pub fn is_empty(&self) -> bool { 0 == self.len() }
+
+    #[inline] pub fn capacity(&self) -> usize { self.slots.len() }
+
+    #[inline] fn slot(&self, n: u32) -> *mut Option<T> {
+        self.slots[n as usize & (self.slots.len() - 1)].get()
+    }
+
+    // Wake the other side if it may be blocked on `index`, which this side just moved.
+    #[inline] fn wake(&self, who: u32, index: &AtomicU32) {
+        if 0 != self.waiting.load(Memord::SeqCst) & who &&
+           0 != self.waiting.fetch_and(!who, Memord::SeqCst) & who { futex_wake(index, 1); }
+    }
+
+    // Block while `*index == val`, as `who`, whom the other side will wake when it moves it.
+    #[inline] fn park(&self, who: u32, index: &AtomicU32, val: u32) {
+        self.waiting.fetch_or(who, Memord::SeqCst);
+        if index.load(Memord::SeqCst) == val { futex_wait(index, val) }
+    }
+}
+
+/// Producing half of a `Ring`
+#[derive(Debug)]
+pub struct Producer<'a, T: 'a> {
+    ring: &'a Ring<'a, T>,
+}
+
+impl<'a, T> Producer<'a, T> {
+    /// Push `x`, blocking while the ring is full.
+    #[inline] pub fn push(&mut self, x: T) {
+        let ring = self.ring;
+        let tail = ring.tail.load(Memord::Relaxed);
+        loop {
+            let head = ring.head.load(Memord::Acquire);
+            if (tail.wrapping_sub(head) as usize) < ring.capacity() { break };
+            ring.park(PRODUCER_WAITING, &ring.head, head);
+        }
+        unsafe { *ring.slot(tail) = Some(x) };
+        ring.tail.store(tail.wrapping_add(1), Memord::SeqCst);
+        ring.wake(CONSUMER_WAITING, &ring.tail);
+    }
+
+    /// Push `x` if the ring is not full, else return it in `Err`.
+    #[inline] pub fn try_push(&mut self, x: T) -> Result<(), T> {
+        let ring = self.ring;
+        let tail = ring.tail.load(Memord::Relaxed);
+        let head = ring.head.load(Memord::Acquire);
+        if tail.wrapping_sub(head) as usize >= ring.capacity() { return Err(x) };
+        unsafe { *ring.slot(tail) = Some(x) };
+        ring.tail.store(tail.wrapping_add(1), Memord::SeqCst);
+        ring.wake(CONSUMER_WAITING, &ring.tail);
+        Ok(())
+    }
+}
+
+/// Consuming half of a `Ring`
+#[derive(Debug)]
+pub struct Consumer<'a, T: 'a> {
+    ring: &'a Ring<'a, T>,
+}
+
+impl<'a, T> Consumer<'a, T> {
+    /// Pop the oldest value, blocking while the ring is empty.
+    #[inline] pub fn pop(&mut self) -> T {
+        let ring = self.ring;
+        let head = ring.head.load(Memord::Relaxed);
+        while ring.tail.load(Memord::Acquire) == head {
+            ring.park(CONSUMER_WAITING, &ring.tail, head);
+        }
+        self.take(head)
+    }
+
+    /// Pop the oldest value if the ring is not empty, else return `None`.
+    #[inline] pub fn try_pop(&mut self) -> Option<T> {
+        let head = self.ring.head.load(Memord::Relaxed);
+        if self.ring.tail.load(Memord::Acquire) == head { None } else { Some(self.take(head)) }
+    }
+
+    #[inline] fn take(&mut self, head: u32) -> T {
+        let ring = self.ring;
+        let x = unsafe { (*ring.slot(head)).take().unwrap() };
+        ring.head.store(head.wrapping_add(1), Memord::SeqCst);
+        ring.wake(PRODUCER_WAITING, &ring.head);
+        x
+    }
+}
@@ -0,0 +1,993 @@
+//! Futex backend
+//!
+//! The primitives here need of the system only that it block a thread while a word holds a
+//! value, and wake threads so blocked, which are in the module of each system: in `linux`, by
+//! the futex system calls, in `windows`, by `WaitOnAddress`, in `macos`, by `__ulock_wait`, and
+//! in `openbsd`, `netbsd`, and `redox`, by their own futex calls. That module provides these:
+//!
+//! * `futex_wait`, `futex_wait_timeout`, `futex_wait_until`, and `futex_wait_interruptible`,
+//!   which block, though they may return spuriously; the timed waits return `Ok(false)` if they
+//!   timed out, and `wait_failed` panics with their error otherwise
+//! * `futex_wake`, which returns how many threads it woke, as best the system tells
+//! * `futex_store_wake`, which stores the value and wakes a waiter without touching the word
+//!   after, as the waiter may free it once it sees the value
+//! * `futex_wake_both`, which wakes a waiter of each of two words, in one call if it can
+//! * `cmp_requeue`, which moves waiters from one word to another, or, lacking that, wakes them
+//! * the shared waits and wakes, for primitives in memory shared between processes, where the
+//!   system can share them
+//! * `gettid`, `yield_now`, and `monotonic_now`
+//!
+//! Primitives which only some systems support, such as `RobustMutex`, are in the module of
+//! those systems, and reexported here.
+
+use core::cell::UnsafeCell;
+use core::hint::spin_loop as cpu_relax;
+use core::ptr;
+use core::time::Duration;
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicU64, Ordering as Memord};
+#[cfg(feature = "named-locks")] use core::sync::atomic::AtomicBool;
+
+use backoff::{Backoff, DefaultRelax, Relax, SpinPolicy, default_policy};
+
+#[cfg(target_os = "linux")] pub use linux::*;
+#[cfg(target_os = "windows")] pub use windows::*;
+#[cfg(target_os = "macos")] pub use macos::*;
+#[cfg(target_os = "openbsd")] pub use openbsd::*;
+#[cfg(target_os = "netbsd")] pub use netbsd::*;
+#[cfg(target_os = "redox")] pub use redox::*;
+
+// 0: unlocked
+// 1: locked
+// 2: locked and contended
+// 3: handed off by `unlock_fair` to a thread it woke: held for a thread trying it afresh, which
+// waits as it would were the lock held, but free for a thread which swaps it out having waited,
+// as the woken waiter does; so a thread barges in only if a spurious wake took it for the woken.
+#[derive(Debug)]
+pub struct Mutex {
+    word: AtomicU32,
+    backoff: Backoff,
+    #[cfg(any(debug_assertions, feature = "deadlock-detection"))]
+    owner: AtomicU32,
+    #[cfg(feature = "stats")]
+    n_fast: AtomicU64,
+    #[cfg(feature = "stats")]
+    n_parked: AtomicU64,
+    #[cfg(feature = "named-locks")]
+    name: Option<&'static str>,
+    #[cfg(feature = "named-locks")]
+    registered: AtomicBool,
+}
+
+impl Mutex {
+    #[inline] pub const fn new() -> Self { Mutex::with_backoff(Backoff::Pause) }
+
+    #[inline] pub const fn with_backoff(backoff: Backoff) -> Self { Mutex::build(backoff, None) }
+
+    /// Make a lock which may be listed by `dump_locks` under `name`, once registered.
+    #[cfg(feature = "named-locks")]
+    #[inline] pub const fn new_named(name: &'static str) -> Self {
+        Mutex::build(Backoff::Pause, Some(name))
+    }
+
+    #[cfg_attr(not(feature = "named-locks"), allow(unused_variables))]
+    #[inline] const fn build(backoff: Backoff, name: Option<&'static str>) -> Self {
+        Mutex {
+            word: AtomicU32::new(0),
+            backoff,
+            #[cfg(any(debug_assertions, feature = "deadlock-detection"))]
+            owner: AtomicU32::new(0),
+            #[cfg(feature = "stats")]
+            n_fast: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            n_parked: AtomicU64::new(0),
+            #[cfg(feature = "named-locks")]
+            name,
+            #[cfg(feature = "named-locks")]
+            registered: AtomicBool::new(false),
+        }
+    }
+
+    #[inline] pub fn lock(&self, policy: SpinPolicy) { self.lock_relax::<DefaultRelax>(policy) }
+
+    // As `lock`, but relax between attempts by `R`.
+    #[inline] pub fn lock_relax<R: Relax>(&self, policy: SpinPolicy) {
+        let m = &self.word;
+        let acquire = || m.compare_exchange(0, 1, Memord::Acquire, Memord::Relaxed).is_ok();
+        if self.backoff.spin::<R, _>(policy, acquire) {
+            #[cfg(feature = "stats")] self.n_fast.fetch_add(1, Memord::Relaxed);
+            return self.acquired();
+        }
+        self.lock_contended();
+    }
+
+    #[inline] fn lock_contended(&self) { self.park(arrive(&self.word)) }
+
+    // As `lock_contended`, but for a thread which has waited, as on a CondVar whose notify may
+    // have moved it to wait on the lock, so it takes the lock if handed off.
+    #[inline] fn relock(&self) {
+        let n = self.word.swap(2, Memord::AcqRel);
+        self.park(if held(n) { n } else { 0 })
+    }
+
+    // Having found `n` in the lock word, by `arrive`, wait until this thread takes the lock,
+    // unless `n` is 0, whereupon it has.
+    #[inline] fn park(&self, mut n: u32) {
+        let m = &self.word;
+        if 0 != n {
+            #[cfg(feature = "backtrace")] ::contention::park(m);
+            #[cfg(feature = "deadlock-detection")] ::deadlock::block(self);
+            loop {
+                futex_wait(m, n.max(2));
+                n = m.swap(2, Memord::AcqRel);
+                if !held(n) { break };
+            }
+            #[cfg(feature = "deadlock-detection")] ::deadlock::unblock();
+            #[cfg(feature = "stats")] self.n_parked.fetch_add(1, Memord::Relaxed);
+        } else {
+            #[cfg(feature = "stats")] self.n_fast.fetch_add(1, Memord::Relaxed);
+        }
+        self.acquired();
+    }
+
+    // As `lock`, but give up once `*token` is nonzero. Lacking a wait on multiple words, the
+    // thread parks for a while at a time to check it.
+    #[inline] pub fn lock_cancelable(&self, policy: SpinPolicy, token: &AtomicU32) -> bool {
+        let m = &self.word;
+        let mut canceled = false;
+        if self.backoff.spin::<DefaultRelax, _>(policy, || {
+            canceled = 0 != token.load(Memord::Acquire);
+            canceled || m.compare_exchange(0, 1, Memord::Acquire, Memord::Relaxed).is_ok()
+        }) {
+            if canceled { return false };
+            #[cfg(feature = "stats")] self.n_fast.fetch_add(1, Memord::Relaxed);
+            self.acquired();
+            return true;
+        }
+        let mut n = arrive(m);
+        if 0 != n {
+            #[cfg(feature = "backtrace")] ::contention::park(m);
+            #[cfg(feature = "deadlock-detection")] ::deadlock::block(self);
+            loop {
+                if 0 != token.load(Memord::Acquire) {
+                    #[cfg(feature = "deadlock-detection")] ::deadlock::unblock();
+                    return false;
+                }
+                futex_wait_timeout(m, n.max(2), CANCEL_POLL).unwrap_or_else(|e| wait_failed(e));
+                n = m.swap(2, Memord::AcqRel);
+                if !held(n) { break };
+            }
+            #[cfg(feature = "deadlock-detection")] ::deadlock::unblock();
+            #[cfg(feature = "stats")] self.n_parked.fetch_add(1, Memord::Relaxed);
+        } else {
+            #[cfg(feature = "stats")] self.n_fast.fetch_add(1, Memord::Relaxed);
+        }
+        self.acquired();
+        true
+    }
+
+    // As `lock`, but give up at `deadline`, in nanoseconds on the monotonic clock.
+    #[inline] pub fn lock_until(&self, policy: SpinPolicy, deadline: u64) -> bool {
+        let m = &self.word;
+        let acquire = || m.compare_exchange(0, 1, Memord::Acquire, Memord::Relaxed).is_ok();
+        if self.backoff.spin::<DefaultRelax, _>(policy, acquire) {
+            #[cfg(feature = "stats")] self.n_fast.fetch_add(1, Memord::Relaxed);
+            self.acquired();
+            return true;
+        }
+        let mut n = arrive(m);
+        if 0 != n {
+            #[cfg(feature = "backtrace")] ::contention::park(m);
+            loop {
+                if !futex_wait_until(m, n.max(2), deadline).unwrap_or_else(|e| wait_failed(e)) {
+                    // The lock may have been handed to this thread as it timed out, which would
+                    // leave it held by none, so take it then.
+                    if m.compare_exchange(HANDOFF, 2, Memord::Acquire, Memord::Relaxed).is_err() {
+                        return false;
+                    }
+                    break;
+                }
+                n = m.swap(2, Memord::AcqRel);
+                if !held(n) { break };
+            }
+            #[cfg(feature = "stats")] self.n_parked.fetch_add(1, Memord::Relaxed);
+        } else {
+            #[cfg(feature = "stats")] self.n_fast.fetch_add(1, Memord::Relaxed);
+        }
+        self.acquired();
+        true
+    }
+
+    #[inline] pub fn unlock(&self, policy: SpinPolicy) {
+        self.unlock_relax::<DefaultRelax>(policy)
+    }
+
+    // As `unlock`, but relax between checks for a new holder by `R`.
+    #[inline] pub fn unlock_relax<R: Relax>(&self, policy: SpinPolicy) {
+        let m = &self.word;
+        self.releasing();
+        if m.swap(0, Memord::Release) == 1 { return };
+        let taken = || m.load(Memord::Acquire) > 0 &&
+                       m.compare_exchange(1, 2, Memord::AcqRel, Memord::Acquire)
+                        .unwrap_or_else(|n| n) > 0;
+        if self.backoff.spin::<R, _>(policy.without_yields(), taken) {
+            return;
+        }
+        futex_wake(m, 1);
+    }
+
+    // As `unlock`, but if any thread waits, hand the lock to it.
+    #[inline] pub fn unlock_fair(&self) { self.hand_off(); }
+
+    // Release the lock as `unlock_fair`; return whether a thread was woken to take it. Should
+    // none be, the waiters having left, the lock is merely unlocked, lest it stay handed off with
+    // none to take it, where `try_lock` would fail.
+    #[inline] fn hand_off(&self) -> bool {
+        let m = &self.word;
+        self.releasing();
+        if m.compare_exchange(1, 0, Memord::Release, Memord::Relaxed).is_ok() { return false };
+        if cfg!(target_os = "windows") {
+            // A wake on Windows tells at most, not how many it woke, so a lock handed off might
+            // be left to none; it is merely unlocked.
+            m.store(0, Memord::Release);
+            futex_wake(m, 1);
+            return false;
+        }
+        m.store(HANDOFF, Memord::Release);
+        if 0 != futex_wake(m, 1) { return true };
+        let _ = m.compare_exchange(HANDOFF, 0, Memord::Relaxed, Memord::Relaxed);
+        false
+    }
+
+    // If any thread waits, hand the lock to it, and take it again after. Lest this take it back
+    // first, it yields while the lock is handed off, but at most `BUMP_YIELDS` times, lest it
+    // wait on a thread slow to take it.
+    #[inline] pub fn bump(&self, policy: SpinPolicy) {
+        if 2 != self.word.load(Memord::Relaxed) { return };
+        if self.hand_off() {
+            let mut k = 0;
+            while HANDOFF == self.word.load(Memord::Relaxed) && k < BUMP_YIELDS {
+                yield_now();
+                k += 1;
+            }
+        }
+        self.lock(policy);
+    }
+
+    #[inline] fn releasing(&self) {
+        debug_assert!(held(self.word.load(Memord::Relaxed)),
+                      "Mutex unlocked while not locked; was a guard dropped twice?");
+        #[cfg(any(debug_assertions, feature = "deadlock-detection"))] {
+            debug_assert_eq!(gettid(), self.owner.load(Memord::Relaxed),
+                             "Mutex unlocked by a thread other than that which locked it");
+            self.owner.store(0, Memord::Relaxed);
+        }
+    }
+
+    #[inline] pub fn try_lock(&self) -> bool {
+        if self.word.compare_exchange(0, 1, Memord::Acquire, Memord::Relaxed).is_ok() {
+            #[cfg(feature = "stats")] self.n_fast.fetch_add(1, Memord::Relaxed);
+            self.acquired();
+            true
+        } else { false }
+    }
+
+    // As `try_lock`, but may fail spuriously; where LL/SC implements the CAS, a failure then
+    // costs no barrier.
+    #[inline] pub fn fast_try_lock(&self) -> bool {
+        if self.word.compare_exchange_weak(0, 1, Memord::Acquire, Memord::Relaxed).is_ok() {
+            #[cfg(feature = "stats")] self.n_fast.fetch_add(1, Memord::Relaxed);
+            self.acquired();
+            true
+        } else { false }
+    }
+
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { &self.word }
+
+    #[inline] pub fn is_locked(&self) -> bool { 0 != self.word.load(Memord::Relaxed) }
+
+    #[inline] pub fn is_contended(&self) -> bool { 2 == self.word.load(Memord::Relaxed) }
+
+    /// Make the calling thread the owner, to release a lock taken by another thread.
+    #[inline] pub fn adopt(&self) { self.acquired() }
+
+    #[inline] fn acquired(&self) {
+        #[cfg(any(debug_assertions, feature = "deadlock-detection"))]
+        self.owner.store(gettid(), Memord::Relaxed);
+    }
+
+    /// List the lock in `dump_locks`, if it was made by `new_named` and is not yet listed. It
+    /// must be `'static`, as it is listed for good.
+    #[cfg(feature = "named-locks")]
+    #[inline] pub fn register(&'static self) {
+        if let Some(name) = self.name {
+            if !self.registered.swap(true, Memord::Relaxed) { ::registry::register(name, self) }
+        }
+    }
+
+    /// 0 if unlocked, 1 if locked, 2 if locked and contended, 3 if handed off
+    #[cfg(feature = "named-locks")]
+    #[inline] pub fn state(&self) -> u32 { self.word.load(Memord::Relaxed) }
+
+    /// Thread which holds the lock, or 0 if none or not known
+    #[cfg(any(debug_assertions, feature = "deadlock-detection"))]
+    #[inline] pub fn owner(&self) -> u32 { self.owner.load(Memord::Relaxed) }
+
+    #[cfg(not(any(debug_assertions, feature = "deadlock-detection")))]
+    #[inline] pub fn owner(&self) -> u32 { 0 }
+
+    /// Numbers of acquisitions without and with parking
+    #[cfg(feature = "stats")]
+    #[inline] pub fn stats(&self) -> (u64, u64) {
+        (self.n_fast.load(Memord::Relaxed), self.n_parked.load(Memord::Relaxed))
+    }
+}
+
+const HANDOFF: u32 = 3;
+
+const BUMP_YIELDS: usize = 0x10;
+
+// Whether a lock word swapped out of a Mutex by a thread which has waited shows it was held, so
+// the swapper must wait again; a lock handed off is the swapper's.
+#[inline] fn held(n: u32) -> bool { n > 0 && n != HANDOFF }
+
+// Mark the lock contended, as a thread trying it afresh; return what was in it, so 0 if this
+// took it, or `HANDOFF`, leaving it be, if it was handed off, so held for this.
+#[inline] fn arrive(m: &AtomicU32) -> u32 {
+    let mut n = m.load(Memord::Relaxed);
+    while HANDOFF != n {
+        match m.compare_exchange_weak(n, 2, Memord::AcqRel, Memord::Relaxed) {
+            Ok(n) => return n,
+            Err(k) => n = k,
+        }
+    }
+    n
+}
+
+const CANCEL_POLL: Duration = Duration::from_millis(1);
+
+// Waiters queue in order, each on a node on its own stack, which the thread before it links to
+// it, and the unlocker hands the lock directly to the first, so it is never free while any
+// thread waits. The holder's node is `head`, in the lock, so it needn't outlive `lock`: a thread
+// granted the lock moves its successor, if any, to `head.next`, and puts `head` in its place at
+// the tail if it has none. `tail` is the last node, `head` if the lock is held without waiters,
+// or null if free.
+#[derive(Debug)]
+pub struct FairMutex {
+    head: Waiter,
+    tail: AtomicPtr<Waiter>,
+}
+
+// 0: waiting
+// 1: holding the lock
+#[derive(Debug)]
+struct Waiter {
+    next: AtomicPtr<Waiter>,
+    word: AtomicU32,
+}
+
+impl Waiter {
+    #[inline] const fn new() -> Self {
+        Waiter { next: AtomicPtr::new(ptr::null_mut()), word: AtomicU32::new(0) }
+    }
+
+    // Wait until a thread which swapped this out of the tail links its own node.
+    #[inline] fn next(&self) -> *mut Waiter {
+        loop {
+            let next = self.next.load(Memord::Acquire);
+            if !next.is_null() { return next };
+            cpu_relax();
+        }
+    }
+}
+
+impl FairMutex {
+    #[inline] pub const fn new() -> Self {
+        FairMutex { head: Waiter::new(), tail: AtomicPtr::new(ptr::null_mut()) }
+    }
+
+    #[inline] fn head(&self) -> *mut Waiter { &self.head as *const _ as *mut _ }
+
+    #[inline] pub fn lock(&self) {
+        let w = Waiter::new();
+        let p = &w as *const _ as *mut _;
+        let mut tail = self.tail.load(Memord::Relaxed);
+        loop {
+            if tail.is_null() {
+                match self.tail.compare_exchange(tail, self.head(), Memord::Acquire,
+                                                 Memord::Relaxed) {
+                    Ok(_) => return,
+                    Err(k) => { tail = k; continue },
+                }
+            }
+            match self.tail.compare_exchange(tail, p, Memord::AcqRel, Memord::Relaxed) {
+                Ok(_) => break,
+                Err(k) => tail = k,
+            }
+        }
+        unsafe { (*tail).next.store(p, Memord::Release) };
+        while 0 == w.word.load(Memord::Acquire) { futex_wait(&w.word, 0) }
+        let next = w.next.load(Memord::Acquire);
+        if !next.is_null() { return self.head.next.store(next, Memord::Relaxed) };
+        self.head.next.store(ptr::null_mut(), Memord::Relaxed);
+        if self.tail.compare_exchange(p, self.head(), Memord::AcqRel, Memord::Relaxed).is_err() {
+            self.head.next.store(w.next(), Memord::Relaxed);
+        }
+    }
+
+    #[inline] pub fn unlock(&self) {
+        let mut next = self.head.next.load(Memord::Acquire);
+        if next.is_null() {
+            if self.tail.compare_exchange(self.head(), ptr::null_mut(), Memord::Release,
+                                          Memord::Relaxed).is_ok() { return };
+            next = self.head.next();
+        }
+        // The waiter may return and free its node once its word is set, so nothing may touch the
+        // node after.
+        unsafe { futex_store_wake(&(*next).word, 1) };
+    }
+
+    #[inline] pub fn try_lock(&self) -> bool {
+        self.tail.compare_exchange(ptr::null_mut(), self.head(), Memord::Acquire,
+                                   Memord::Relaxed).is_ok()
+    }
+}
+
+// state: phase in the high half, number of threads arrived in the low half; while the last to
+// arrive releases the rest, the number is `n_total`, and others wait for it to be reset.
+// seq: phase, stored before the state moves to the next phase, so a thread which arrives in the
+// next phase never sees the former.
+// shared: whether waits and wakes are shared, for a barrier in memory shared between processes,
+// which is why the layout is fixed.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Barrier {
+    state: AtomicU64,
+    n_total: usize,
+    spins: usize,
+    seq: AtomicU32,
+    shared: bool,
+}
+
+impl Barrier {
+    #[inline] pub const fn new(n: usize) -> Self { Barrier::with_spins(n, ::DEFAULT_SPINS) }
+
+    #[inline] pub const fn with_spins(n: usize, spins: usize) -> Self {
+        Barrier::build(n, spins, false)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[inline] pub const fn new_shared(n: usize) -> Self {
+        Barrier::build(n, ::DEFAULT_SPINS, true)
+    }
+
+    #[inline] const fn build(n: usize, spins: usize, shared: bool) -> Self {
+        Barrier {
+            state: AtomicU64::new(0),
+            n_total: n,
+            spins,
+            seq: AtomicU32::new(0 /* mem::uninitialized */),
+            shared,
+        }
+    }
+
+    #[inline] fn park(&self, phase: u32) {
+        if self.shared { futex_wait_shared(&self.seq, phase) }
+        else { futex_wait(&self.seq, phase) }
+    }
+
+    #[inline] fn park_until(&self, phase: u32, deadline: u64) -> bool {
+        let r = if self.shared { futex_wait_until_shared(&self.seq, phase, deadline) }
+                else { futex_wait_until(&self.seq, phase, deadline) };
+        r.unwrap_or_else(|e| wait_failed(e))
+    }
+
+    #[inline] fn wake_all(&self) {
+        if self.shared { futex_wake_shared(&self.seq, !0); } else { futex_wake(&self.seq, !0); }
+    }
+
+    /// Count the caller in; return the phase in which it arrived, and whether it was the last,
+    /// whereupon it has run `f` and then released the rest.
+    #[inline] fn arrive<F: FnOnce()>(&self, n_total: usize, f: F) -> (u32, bool) {
+        let mut state = self.state.load(Memord::Relaxed);
+        loop {
+            let (phase, n) = ((state >> 32) as u32, state as u32 as usize);
+            if n >= n_total {
+                cpu_relax();
+                state = self.state.load(Memord::Relaxed);
+                continue;
+            }
+            if let Err(old) = self.state.compare_exchange(state, state + 1, Memord::AcqRel,
+                                                             Memord::Relaxed) {
+                state = old;
+                continue;
+            }
+            if n + 1 < n_total { return (phase, false) };
+            let _release = Release(self, phase.wrapping_add(1));
+            f();
+            return (phase, true);
+        }
+    }
+
+    /// Count the caller out of `phase`, unless it was already released from it; return whether
+    /// this counted it out.
+    #[inline] fn leave(&self, phase: u32) -> bool {
+        let mut state = self.state.load(Memord::Relaxed);
+        loop {
+            if (state >> 32) as u32 != phase || state as u32 as usize >= self.n_total {
+                return false;
+            }
+            match self.state.compare_exchange(state, state - 1, Memord::Relaxed, Memord::Relaxed) {
+                Ok(_) => return true,
+                Err(old) => state = old,
+            }
+        }
+    }
+
+    // Spin a while in the hope of being released without parking.
+    #[inline] fn spin(&self, phase: u32) {
+        for _ in 0..self.spins {
+            if self.seq.load(Memord::Relaxed) != phase { return };
+            cpu_relax();
+        }
+    }
+
+    #[inline] pub fn wait(&self) -> bool { self.wait_n(self.n_total) }
+
+    /// As `wait`, for `n_total` threads rather than the number given at construction; every
+    /// thread must give the same.
+    #[inline] pub fn wait_n(&self, n_total: usize) -> bool { self.wait_n_with(n_total, || ()).1 }
+
+    /// As `wait`, but the last thread to arrive runs `f` before it releases the rest.
+    #[inline] pub fn wait_with<F: FnOnce()>(&self, f: F) -> bool {
+        self.wait_n_with(self.n_total, f).1
+    }
+
+    /// As `wait`, but return the phase which ended too.
+    #[inline] pub fn wait_phase(&self) -> (u32, bool) { self.wait_n_with(self.n_total, || ()) }
+
+    #[inline] fn wait_n_with<F: FnOnce()>(&self, n_total: usize, f: F) -> (u32, bool) {
+        let (phase, last) = self.arrive(n_total, f);
+        self.spin(phase);
+        while self.seq.load(Memord::Acquire) == phase { self.park(phase) }
+        (phase, last)
+    }
+
+    #[inline] pub fn wait_until(&self, deadline: u64) -> Option<bool> {
+        let (phase, last) = self.arrive(self.n_total, || ());
+        self.spin(phase);
+        while self.seq.load(Memord::Acquire) == phase {
+            if !self.park_until(phase, deadline) && self.leave(phase) { return None }
+        }
+        Some(last)
+    }
+
+    #[inline] pub fn waiting(&self) -> usize {
+        (self.state.load(Memord::Relaxed) as u32 as usize).min(self.n_total)
+    }
+
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { &self.seq }
+
+    #[inline] pub fn phase(&self) -> u32 { self.seq.load(Memord::Acquire) }
+
+    #[inline] pub fn release_to_phase(&self, phase: u32) {
+        let seq = self.seq.swap(phase, Memord::Release);
+        debug_assert_ne!(seq, phase, "Barrier released to its current phase");
+        self.state.store((phase as u64) << 32, Memord::Release);
+        self.wake_all();
+    }
+}
+
+// Release of a barrier to the phase given, when dropped, even by a panic of the last thread's
+// action, lest it hang the rest
+struct Release<'a>(&'a Barrier, u32);
+
+impl<'a> Drop for Release<'a> {
+    #[inline] fn drop(&mut self) {
+        let Release(b, phase) = *self;
+        b.seq.store(phase, Memord::Release);
+        b.state.store((phase as u64) << 32, Memord::Release);
+        b.wake_all();
+    }
+}
+
+// counts: in the low half, threads between `enter` and `leave`; if coalescing, in the high half,
+// wakes issued since they entered, each of which will make one of them return, and of which
+// each consumes one, if any, as it leaves. So there are never more wakes than waiters, and
+// notify_one skips the system call if there are as many. A waiter loads `seq` before it counts
+// itself in, and a notifier counts its wakes before it bumps `seq`, so a wake counted for a
+// waiter always changes the number it waits on, and it can't sleep through the wake.
+// ptr: the word of the mutex the waiters hold, set by the first to enter while none are in; as
+// each enters and leaves holding it, only a thread of another mutex can find it set meanwhile.
+// So once all have left, the CondVar may be used with another mutex.
+#[derive(Debug)]
+pub struct CondVar {
+    ptr: AtomicPtr<AtomicU32>,
+    seq: AtomicU32,
+    coalescing: bool,
+    counts: AtomicU64,
+    #[cfg(feature = "stats")]
+    n_wake_calls: AtomicU64,
+}
+
+impl CondVar {
+    #[inline] pub const fn new() -> Self { CondVar::with_coalescing(false) }
+
+    #[inline] pub const fn with_coalescing(coalescing: bool) -> Self {
+        CondVar {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+            seq: AtomicU32::new(0 /* mem::uninitialized */),
+            coalescing,
+            counts: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            n_wake_calls: AtomicU64::new(0),
+        }
+    }
+
+    // Release `m`, returning the sequence number to wait on.
+    #[inline] fn enter(&self, m: &Mutex) -> u32 {
+        debug_assert_ne!(0, m.word.load(Memord::Relaxed));
+
+        let mut seq = self.seq.load(Memord::SeqCst);
+        let word = &m.word as *const _ as *mut _;
+        if 0 == self.counts.fetch_add(1, Memord::SeqCst) & 0xFFFF_FFFF {
+            // Lest `notify_all` move threads waiting with this mutex to the last, whose word it
+            // may have loaded, change the sequence number, on which its move is conditioned;
+            // wait on the new number, unless a notification changed it meanwhile, whereupon the
+            // wait returns at once.
+            if self.ptr.swap(word, Memord::SeqCst) != word &&
+               seq == self.seq.fetch_add(1, Memord::SeqCst) {
+                seq = seq.wrapping_add(1);
+            }
+        } else {
+            debug_assert_eq!(self.ptr.load(Memord::Relaxed), word,
+                             "CondVar used with multiple Mutexen");
+        }
+
+        m.unlock(default_policy());
+        seq
+    }
+
+    // Having taken the mutex again, count the thread out.
+    #[inline] fn leave(&self) {
+        let mut counts = self.counts.load(Memord::Relaxed);
+        loop {
+            let new = if counts >> 32 > 0 { counts - (1 << 32) - 1 } else { counts - 1 };
+            match self.counts.compare_exchange(counts, new, Memord::Relaxed, Memord::Relaxed) {
+                Ok(_) => break,
+                Err(old) => counts = old,
+            }
+        }
+    }
+
+    #[inline] pub fn wait(&self, m: &Mutex) {
+        let seq = self.enter(m);
+        futex_wait(&self.seq, seq);
+        m.relock();
+        self.leave();
+    }
+
+    /// As `wait`, but return `false` if a signal interrupted the wait.
+    #[inline] pub fn wait_interruptible(&self, m: &Mutex) -> bool {
+        let seq = self.enter(m);
+        let r = futex_wait_interruptible(&self.seq, seq);
+        m.relock();
+        self.leave();
+        r
+    }
+
+    /// As `wait`, but return `false` if `deadline`, in nanoseconds on the monotonic clock,
+    /// passed first.
+    #[inline] pub fn wait_until(&self, m: &Mutex, deadline: u64) -> bool {
+        let seq = self.enter(m);
+        let r = futex_wait_until(&self.seq, seq, deadline);
+        m.relock();
+        self.leave();
+        r.unwrap_or_else(|e| wait_failed(e))
+    }
+
+    #[inline] pub fn futex_addr(&self) -> *const AtomicU32 { &self.seq }
+
+    #[inline] pub fn reset(&mut self) {
+        *self.ptr.get_mut() = ptr::null_mut();
+        *self.seq.get_mut() = 0;
+        *self.counts.get_mut() = 0;
+    }
+
+    #[inline] pub fn notify_one(&self) -> usize {
+        if 0 == self.claim_wakes(1) { return 0 };
+        self.seq.fetch_add(1, Memord::SeqCst);
+        self.wake_call();
+        futex_wake(&self.seq, 1)
+    }
+
+    // As `notify_one`, by a thread holding `m`, but move the waiter to wait on `m` rather than
+    // waking it, so it wakes once `m` is released, rather than at once only to block on it.
+    #[inline] pub fn notify_one_locked(&self, m: &Mutex) -> usize {
+        debug_assert!(held(m.word.load(Memord::Relaxed)));
+        if 0 == self.claim_wakes(1) { return 0 };
+        let mut seq = self.seq.fetch_add(1, Memord::SeqCst).wrapping_add(1);
+        loop {
+            self.wake_call();
+            match cmp_requeue(&self.seq, seq, 0, &m.word, 1) {
+                Some(0) => return 0,
+                // The waiter now waits on `m`, so mark `m` contended, lest its unlock not wake it.
+                Some(n) => { m.word.store(2, Memord::Relaxed); return n },
+                None => seq = self.seq.load(Memord::SeqCst),
+            }
+        }
+    }
+
+    // As `notify_one`, by a thread holding `m`, and then unlock `m`; if `m` is contended, this
+    // wakes a waiter of each in one system call rather than two. Return how many threads were
+    // woken in all.
+    #[inline] pub fn notify_one_unlock(&self, m: &Mutex) -> usize {
+        if 0 == self.claim_wakes(1) {
+            m.unlock(default_policy());
+            return 0;
+        }
+        self.seq.fetch_add(1, Memord::SeqCst);
+        m.releasing();
+        self.wake_call();
+        match m.word.swap(0, Memord::Release) {
+            2 => futex_wake_both(&self.seq, &m.word),
+            _ => futex_wake(&self.seq, 1),
+        }
+    }
+
+    // If coalescing, count up to `n` wakes, but no more than make as many as waiters; return how
+    // many to issue.
+    #[inline] fn claim_wakes(&self, n: usize) -> usize {
+        if !self.coalescing { return n };
+        let mut counts = self.counts.load(Memord::SeqCst);
+        loop {
+            let k = ((counts & 0xFFFF_FFFF).saturating_sub(counts >> 32) as usize).min(n);
+            if 0 == k { return 0 };
+            match self.counts.compare_exchange(counts, counts + ((k as u64) << 32),
+                                               Memord::SeqCst, Memord::SeqCst) {
+                Ok(_) => return k,
+                Err(old) => counts = old,
+            }
+        }
+    }
+
+    #[inline] pub fn notify_n(&self, n: usize) -> usize {
+        if 0 == n { return 0 };
+        let n = self.claim_wakes(n);
+        if 0 == n { return 0 };
+        self.seq.fetch_add(1, Memord::SeqCst);
+        self.wake_requeue(n)
+    }
+
+    #[inline] pub fn notify_all(&self) -> usize {
+        self.seq.fetch_add(1, Memord::SeqCst);
+        self.wake_requeue(!0)
+    }
+
+    // Wake one waiter, and move up to `n - 1` more to wait on the mutex, so they wake one at a
+    // time as it is released, rather than all at once to contend for it; return how many were
+    // woken or moved. This needs no lock: the thread woken takes the mutex by `relock`,
+    // which marks it contended, so its unlock wakes the next, and so on. The mutex may be gone
+    // if no thread waits, but then none is moved.
+    #[inline] fn wake_requeue(&self, n: usize) -> usize {
+        loop {
+            let seq = self.seq.load(Memord::SeqCst);
+            let ptr = self.ptr.load(Memord::SeqCst);
+            if ptr.is_null() { return 0 };
+            self.wake_call();
+            if let Some(k) = cmp_requeue(&self.seq, seq, 1, ptr, n - 1) { return k };
+        }
+    }
+
+    #[inline] fn wake_call(&self) {
+        #[cfg(feature = "stats")] self.n_wake_calls.fetch_add(1, Memord::Relaxed);
+    }
+
+    /// Number of system calls made to wake or move waiters
+    #[cfg(feature = "stats")]
+    #[inline] pub fn stats(&self) -> u64 { self.n_wake_calls.load(Memord::Relaxed) }
+}
+
+// Locks held by each thread, in debug builds, so that it may panic rather than wait on itself:
+// the thread, the address of each lock, and whether it is held for writing. Lacking a
+// thread-local without `std`, the table is shared by all threads, as that of `deadlock`. Should
+// it fill, further locks go unchecked; a thread which exits holding a lock leaves its entry.
+#[cfg(debug_assertions)]
+mod held {
+    use core::cell::UnsafeCell;
+    use core::hint::spin_loop as cpu_relax;
+    use core::sync::atomic::{AtomicBool, Ordering as Memord};
+
+    use system::gettid;
+
+    const MAX_HELD: usize = 0x100;
+
+    struct Table {
+        lock: AtomicBool,
+        held: UnsafeCell<[(u32, usize, bool); MAX_HELD]>,
+    }
+
+    unsafe impl Sync for Table {}
+
+    static TABLE: Table = Table {
+        lock: AtomicBool::new(false),
+        held: UnsafeCell::new([(0, 0, false); MAX_HELD]),
+    };
+
+    #[inline]
+    fn with_table<A, F: FnOnce(&mut [(u32, usize, bool); MAX_HELD]) -> A>(f: F) -> A {
+        while TABLE.lock.compare_exchange_weak(false, true, Memord::Acquire, Memord::Relaxed)
+                        .is_err() { cpu_relax() }
+        let a = f(unsafe { &mut *TABLE.held.get() });
+        TABLE.lock.store(false, Memord::Release);
+        a
+    }
+
+    // Panic if the calling thread holds `lock` at all. Even a second read may wait forever, if a
+    // writer came between and bars more readers until the first leaves.
+    #[inline] pub fn check(lock: usize, write: bool) {
+        let tid = gettid();
+        let h = with_table(|held| held.iter().find(|h| (tid, lock) == (h.0, h.1)).cloned());
+        if let Some(h) = h {
+            panic!("RwLock {} by the thread which holds it for {}",
+                   if write { "write-locked" } else { "read-locked" },
+                   if h.2 { "writing" } else { "reading" })
+        }
+    }
+
+    #[inline] pub fn acquired(lock: usize, write: bool) {
+        let tid = gettid();
+        with_table(|held| if let Some(h) = held.iter_mut().find(|h| 0 == h.1) {
+            *h = (tid, lock, write)
+        })
+    }
+
+    #[inline] pub fn released(lock: usize) {
+        let tid = gettid();
+        with_table(|held| if let Some(h) = held.iter_mut().find(|h| (tid, lock) == (h.0, h.1)) {
+            *h = (0, 0, false)
+        })
+    }
+}
+
+// readers: number of readers holding the lock, or'd with WRITER if a writer holds it or waits
+// for them to leave, whereupon no more may enter. Writers are serialized by `writer`; readers and
+// the writer all wait on `readers`.
+#[derive(Debug)]
+pub struct RwLock {
+    readers: AtomicU32,
+    writer: Mutex,
+}
+
+const WRITER: u32 = 1 << 31;
+
+impl RwLock {
+    #[inline] pub const fn new() -> Self {
+        RwLock { readers: AtomicU32::new(0), writer: Mutex::new() }
+    }
+
+    #[inline] pub fn read(&self, spins: usize) {
+        #[cfg(debug_assertions)] held::check(self.addr(), false);
+        let mut n = self.readers.load(Memord::Relaxed);
+        let mut k = 0;
+        loop {
+            if n & WRITER == 0 {
+                if let Err(m) = self.readers.compare_exchange(n, n + 1, Memord::Acquire,
+                                                              Memord::Relaxed) {
+                    n = m;
+                    continue;
+                }
+                #[cfg(debug_assertions)] held::acquired(self.addr(), false);
+                return;
+            }
+            if k < spins { k += 1; cpu_relax(); } else { futex_wait(&self.readers, n); }
+            n = self.readers.load(Memord::Relaxed);
+        }
+    }
+
+    #[inline] pub fn try_read(&self) -> bool {
+        #[cfg(debug_assertions)] held::check(self.addr(), false);
+        let mut n = self.readers.load(Memord::Relaxed);
+        while n & WRITER == 0 {
+            match self.readers.compare_exchange(n, n + 1, Memord::Acquire, Memord::Relaxed) {
+                Ok(_) => {
+                    #[cfg(debug_assertions)] held::acquired(self.addr(), false);
+                    return true;
+                },
+                Err(m) => n = m,
+            }
+        }
+        false
+    }
+
+    #[inline] pub fn read_unlock(&self) {
+        #[cfg(debug_assertions)] held::released(self.addr());
+        let n = self.readers.fetch_sub(1, Memord::Release);
+        debug_assert_ne!(0, n & !WRITER, "RwLock read-unlocked while not read-locked");
+        if WRITER | 1 == n { futex_wake(&self.readers, !0); }
+    }
+
+    #[inline] pub fn write(&self, spins: usize) {
+        #[cfg(debug_assertions)] held::check(self.addr(), true);
+        self.writer.lock(SpinPolicy { spins, ..default_policy() });
+        let mut n = self.readers.fetch_or(WRITER, Memord::Acquire) | WRITER;
+        let mut k = 0;
+        while WRITER != n {
+            if k < spins { k += 1; cpu_relax(); } else { futex_wait(&self.readers, n); }
+            n = self.readers.load(Memord::Acquire);
+        }
+        #[cfg(debug_assertions)] held::acquired(self.addr(), true);
+    }
+
+    #[inline] pub fn try_write(&self) -> bool {
+        #[cfg(debug_assertions)] held::check(self.addr(), true);
+        if !self.writer.try_lock() { return false };
+        if self.readers.compare_exchange(0, WRITER, Memord::Acquire, Memord::Relaxed).is_ok() {
+            #[cfg(debug_assertions)] held::acquired(self.addr(), true);
+            return true;
+        }
+        self.writer.unlock(SpinPolicy::PARK);
+        false
+    }
+
+    #[inline] pub fn write_unlock(&self, spins: usize) {
+        debug_assert_eq!(WRITER, self.readers.load(Memord::Relaxed),
+                         "RwLock write-unlocked while not write-locked");
+        #[cfg(debug_assertions)] held::released(self.addr());
+        self.readers.store(0, Memord::Release);
+        futex_wake(&self.readers, !0);
+        self.writer.unlock(SpinPolicy { spins, ..default_policy() });
+    }
+
+    #[cfg(debug_assertions)]
+    #[inline] fn addr(&self) -> usize { self as *const Self as usize }
+}
+
+// owner: thread holding the lock, or 0; only it writes count, and only it reads owner as its own.
+#[derive(Debug)]
+pub struct ReentrantMutex {
+    lock: Mutex,
+    owner: AtomicU32,
+    count: UnsafeCell<usize>,
+}
+
+unsafe impl Sync for ReentrantMutex {}
+
+impl ReentrantMutex {
+    #[inline] pub const fn new() -> Self {
+        ReentrantMutex { lock: Mutex::new(), owner: AtomicU32::new(0), count: UnsafeCell::new(0) }
+    }
+
+    #[inline] pub fn lock(&self, spins: usize) {
+        let tid = gettid();
+        if tid != self.owner.load(Memord::Relaxed) {
+            self.lock.lock(SpinPolicy { spins, ..default_policy() });
+            self.owner.store(tid, Memord::Relaxed);
+        }
+        self.enter();
+    }
+
+    #[inline] pub fn try_lock(&self) -> bool {
+        let tid = gettid();
+        if tid != self.owner.load(Memord::Relaxed) {
+            if !self.lock.try_lock() { return false };
+            self.owner.store(tid, Memord::Relaxed);
+        }
+        self.enter();
+        true
+    }
+
+    #[inline] fn enter(&self) { unsafe {
+        *self.count.get() = (*self.count.get()).checked_add(1)
+                                               .expect("ReentrantMutex locked too many times");
+    } }
+
+    #[inline] pub fn unlock(&self, spins: usize) { unsafe {
+        debug_assert_eq!(gettid(), self.owner.load(Memord::Relaxed),
+                         "ReentrantMutex unlocked by a thread other than that which locked it");
+        *self.count.get() -= 1;
+        if 0 == *self.count.get() {
+            self.owner.store(0, Memord::Relaxed);
+            self.lock.unlock(SpinPolicy { spins, ..default_policy() });
+        }
+    } }
+}
@@ -0,0 +1,184 @@
+//! Backends of the blocking primitives
+//!
+//! `Mutex`, `CondVar` and `Barrier` are generic over the system which blocks and wakes threads: by
+//! default `DefaultSystem`, which is built on futexes, or their like on Windows and macOS, or,
+//! with `critical-section`, on disabling interrupts. Another may be supplied, e.g. an RTOS's own
+//! primitives, by implementing these traits for a type of one's own; the wrappers, guards, and
+//! `CondVar` logic atop are then the same. Such locks have only the portable methods: what is
+//! specific to this crate's own backend, such as deadlines, cancelation, fairness, or
+//! `futex_addr`, is for `DefaultSystem`.
+//!
+//! ```
+//! # extern crate user_sync;
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//! use user_sync::{Mutex, SystemSync};
+//!
+//! // A spin lock, as a stand-in for a system's own lock
+//! #[derive(Debug)]
+//! struct Spin;
+//!
+//! unsafe impl SystemSync for Spin {
+//!     type Mutex = AtomicBool;
+//!     const MUTEX_INIT: AtomicBool = AtomicBool::new(false);
+//!     fn lock(m: &AtomicBool) { while !Self::try_lock(m) {} }
+//!     fn try_lock(m: &AtomicBool) -> bool { !m.swap(true, Ordering::Acquire) }
+//!     unsafe fn unlock(m: &AtomicBool) { m.store(false, Ordering::Release) }
+//!     fn is_locked(m: &AtomicBool) -> bool { m.load(Ordering::Relaxed) }
+//! }
+//!
+//! # fn main() {
+//! let mutex: Mutex<u32, Spin> = Mutex::with_system(0);
+//! *mutex.lock() += 1;
+//! assert_eq!(1, *mutex.lock());
+//! # }
+//! ```
+
+#[cfg(not(feature = "critical-section"))] use core::marker::PhantomData;
+
+#[cfg(not(feature = "critical-section"))] use backoff::{DefaultRelax, Relax};
+
+/// Lock beneath `Mutex`
+///
+/// # Safety
+///
+/// `lock`, and `try_lock` where it returns `true`, must take the lock exclusively, and order
+/// what the caller does then after what the last holder did before `unlock`.
+pub unsafe trait SystemSync {
+    /// State of one lock
+    type Mutex: Send + Sync;
+
+    /// Unlocked lock
+    const MUTEX_INIT: Self::Mutex;
+
+    /// Take the lock, blocking while another thread holds it.
+    fn lock(m: &Self::Mutex);
+
+    /// Take the lock if no thread holds it, else return `false`.
+    fn try_lock(m: &Self::Mutex) -> bool;
+
+    /// Release the lock.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held, and the calling thread deemed its holder.
+    unsafe fn unlock(m: &Self::Mutex);
+
+    /// Deem the calling thread the holder of the lock, which another thread took, so that it
+    /// may release it; by default, do nothing, for locks which don't track their holder.
+    #[inline] fn adopt(_m: &Self::Mutex) {}
+
+    /// Whether some thread holds the lock; only advisory
+    fn is_locked(m: &Self::Mutex) -> bool;
+}
+
+/// Condition variable beneath `CondVar`
+///
+/// # Safety
+///
+/// A thread in `wait` must return once notified after it released the lock; it may return
+/// spuriously too.
+pub unsafe trait SystemCondVar: SystemSync {
+    /// State of one condition variable
+    type CondVar: Send + Sync;
+
+    /// Condition variable with no waiters
+    const CONDVAR_INIT: Self::CondVar;
+
+    /// Release `m`, block until notified, and take `m` again.
+    ///
+    /// # Safety
+    ///
+    /// The calling thread must hold `m`.
+    unsafe fn wait(c: &Self::CondVar, m: &Self::Mutex);
+
+    /// Unblock one waiting thread; return how many were notified, 0 or 1.
+    fn notify_one(c: &Self::CondVar) -> usize;
+
+    /// Unblock at most `n` waiting threads; return how many were notified. By default, this
+    /// notifies them one at a time until `n` are or none is left.
+    #[inline] fn notify_n(c: &Self::CondVar, n: usize) -> usize {
+        let mut k = 0;
+        while k < n && 0 != Self::notify_one(c) { k += 1 }
+        k
+    }
+
+    /// Unblock all waiting threads; return how many were notified.
+    fn notify_all(c: &Self::CondVar) -> usize;
+}
+
+/// Barrier beneath `Barrier`
+///
+/// # Safety
+///
+/// `barrier_wait` must return only once the number of threads the barrier was made for have
+/// called it, in the same phase, and then `true` in exactly one of them; and the barrier must
+/// then be reusable.
+pub unsafe trait SystemBarrier {
+    /// State of one barrier
+    type Barrier: Send + Sync;
+
+    /// Make a barrier for `n` threads.
+    fn barrier(n: usize) -> Self::Barrier;
+
+    /// Wait until all threads reach the barrier.
+    fn barrier_wait(b: &Self::Barrier) -> bool;
+}
+
+/// This crate's futex backend, whose locks relax by `R` while spinning
+#[cfg(not(feature = "critical-section"))]
+#[derive(Debug)]
+pub struct Futex<R: Relax = DefaultRelax>(PhantomData<R>);
+
+/// This crate's uniprocessor backend, with which a lock is held with interrupts disabled
+#[cfg(feature = "critical-section")]
+#[derive(Debug)]
+pub struct CriticalSection;
+
+/// Backend which the primitives use unless told otherwise
+#[cfg(not(feature = "critical-section"))]
+pub type DefaultSystem = Futex;
+
+/// Backend which the primitives use unless told otherwise
+#[cfg(feature = "critical-section")]
+pub type DefaultSystem = CriticalSection;
+
+#[cfg(not(feature = "critical-section"))]
+unsafe impl<R: Relax> SystemSync for Futex<R> {
+    type Mutex = ::system::Mutex;
+    #[allow(clippy::declare_interior_mutable_const)]
+    const MUTEX_INIT: Self::Mutex = ::system::Mutex::new();
+    #[inline] fn lock(m: &Self::Mutex) { m.lock_relax::<R>(::default_policy()) }
+    #[inline] fn try_lock(m: &Self::Mutex) -> bool { m.try_lock() }
+    #[inline] unsafe fn unlock(m: &Self::Mutex) { m.unlock_relax::<R>(::default_policy()) }
+    #[inline] fn adopt(m: &Self::Mutex) { m.adopt() }
+    #[inline] fn is_locked(m: &Self::Mutex) -> bool { m.is_locked() }
+}
+
+#[cfg(feature = "critical-section")]
+unsafe impl SystemSync for CriticalSection {
+    type Mutex = ::system::Mutex;
+    #[allow(clippy::declare_interior_mutable_const)]
+    const MUTEX_INIT: Self::Mutex = ::system::Mutex::new();
+    #[inline] fn lock(m: &Self::Mutex) { m.lock(::default_policy()) }
+    #[inline] fn try_lock(m: &Self::Mutex) -> bool { m.try_lock() }
+    #[inline] unsafe fn unlock(m: &Self::Mutex) { m.unlock(::default_policy()) }
+    #[inline] fn is_locked(m: &Self::Mutex) -> bool { m.is_locked() }
+}
+
+#[cfg(not(feature = "critical-section"))]
+unsafe impl<R: Relax> SystemCondVar for Futex<R> {
+    type CondVar = ::system::CondVar;
+    #[allow(clippy::declare_interior_mutable_const)]
+    const CONDVAR_INIT: Self::CondVar = ::system::CondVar::new();
+    #[inline] unsafe fn wait(c: &Self::CondVar, m: &Self::Mutex) { c.wait(m) }
+    #[inline] fn notify_one(c: &Self::CondVar) -> usize { c.notify_one() }
+    #[inline] fn notify_n(c: &Self::CondVar, n: usize) -> usize { c.notify_n(n) }
+    #[inline] fn notify_all(c: &Self::CondVar) -> usize { c.notify_all() }
+}
+
+#[cfg(not(feature = "critical-section"))]
+unsafe impl SystemBarrier for Futex {
+    type Barrier = ::system::Barrier;
+    #[inline] fn barrier(n: usize) -> Self::Barrier { ::system::Barrier::new(n) }
+    #[inline] fn barrier_wait(b: &Self::Barrier) -> bool { b.wait() }
+}
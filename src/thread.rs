@@ -0,0 +1,16 @@
+/// Thread identifier
+///
+/// This is the kernel's identifier of the thread, so it is unique among live threads, but may be
+/// reused once a thread exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadId(pub(crate) u32);
+
+impl ThreadId {
+    #[inline] pub fn as_u32(self) -> u32 { self.0 }
+}
+
+/// Let another thread run, if any is ready, before the calling thread continues.
+///
+/// This is for spin loops which may outlast the holder's time slice, such as on an
+/// oversubscribed machine.
+#[inline] pub fn yield_now() { ::system::yield_now() }
@@ -0,0 +1,27 @@
+use core::ops::Add;
+use core::time::Duration;
+
+/// Point in time on the monotonic clock, for deadlines
+///
+/// Unlike a timeout, one deadline may be shared by many waits without the drift of restarting
+/// the timeout at each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(pub(crate) u64);
+
+impl Instant {
+    #[inline] pub fn now() -> Self { Instant(::system::monotonic_now()) }
+
+    /// Time from `earlier` to `self`, or zero if `earlier` is later
+    #[inline] pub fn duration_since(self, earlier: Instant) -> Duration {
+        Duration::from_nanos(self.0.saturating_sub(earlier.0))
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+    #[inline] fn add(self, d: Duration) -> Instant {
+        let nanos = d.as_secs().saturating_mul(1_000_000_000)
+                     .saturating_add(d.subsec_nanos() as u64);
+        Instant(self.0.saturating_add(nanos))
+    }
+}
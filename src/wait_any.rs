@@ -0,0 +1,114 @@
+use core::fmt;
+use core::sync::atomic::AtomicU32;
+use core::time::Duration;
+
+#[cfg(target_os = "linux")] use futex::{self, FutexError, WaitV, WAITV_MAX};
+#[cfg(target_os = "linux")] use linux::ENOSYS;
+use system::futex_wait_timeout;
+use time::Instant;
+
+/// Primitive which `WaitAny` may wait for, among others
+pub trait Waitable {
+    /// Do as `wait` would if it need not block, e.g. take a permit, and return `Ok`; else return
+    /// the futex word, and the value while which `wait` would block on it.
+    fn poll(&self) -> Result<(), (&AtomicU32, u32)>;
+}
+
+/// Wait for whichever of several primitives is first ready, e.g. for work on any of several
+/// queues, or for shutdown
+///
+/// The thread blocks on all their futex words at once, by `futex_waitv`; on kernels older than
+/// 5.16, which lack it, and on other systems, it parks for a while at a time on one of them to
+/// poll the rest.
+///
+/// ```
+/// # extern crate user_sync;
+/// use user_sync::{Flag, Semaphore, WaitAny, Waitable};
+///
+/// # fn main() {
+/// let work = Semaphore::new(0);
+/// let shutdown = Flag::new();
+/// let sources: [&dyn Waitable; 2] = [&work, &shutdown];
+///
+/// work.release();
+/// assert_eq!(0, WaitAny::new(&sources).wait());
+/// shutdown.notify();
+/// assert_eq!(1, WaitAny::new(&sources).wait());
+/// # }
+/// ```
+pub struct WaitAny<'a> {
+    sources: &'a [&'a dyn Waitable],
+}
+
+impl<'a> fmt::Debug for WaitAny<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WaitAny").field("sources", &self.sources.len()).finish()
+    }
+}
+
+const POLL: Duration = Duration::from_millis(1);
+
+#[cfg(not(target_os = "linux"))] const WAITV_MAX: usize = 128;
+
+#[cfg(target_os = "linux")] static NONE: AtomicU32 = AtomicU32::new(0);
+
+impl<'a> WaitAny<'a> {
+    /// # Panics
+    ///
+    /// Panicks if `sources` is empty, or longer than 128, `futex::WAITV_MAX`.
+    #[inline] pub fn new(sources: &'a [&'a dyn Waitable]) -> Self {
+        assert!(!sources.is_empty() && sources.len() <= WAITV_MAX,
+                "WaitAny made over a bad number of sources");
+        WaitAny { sources }
+    }
+
+    /// Block until any source is ready, do as its `wait` would, and return its index.
+    #[inline] pub fn wait(&self) -> usize {
+        match self.wait_deadline(None) { Some(i) => i, None => unreachable!() }
+    }
+
+    /// As `wait`, but give up after `timeout`, returning `None`.
+    #[inline] pub fn wait_timeout(&self, timeout: Duration) -> Option<usize> {
+        self.wait_deadline(Some(Instant::now() + timeout))
+    }
+
+    /// As `wait`, but give up at `deadline`, returning `None`.
+    #[inline] pub fn wait_until(&self, deadline: Instant) -> Option<usize> {
+        self.wait_deadline(Some(deadline))
+    }
+
+    // Poll the sources from that last woken, lest a wake meant for another thread, such as of
+    // a semaphore, be lost while this one takes another source.
+    fn wait_deadline(&self, deadline: Option<Instant>) -> Option<usize> {
+        let n = self.sources.len();
+        #[cfg(target_os = "linux")] let mut ws = [WaitV::new(&NONE, 0); WAITV_MAX];
+        #[cfg_attr(not(target_os = "linux"), allow(unused_mut))] let mut first = 0;
+        loop {
+            let mut park = None;
+            for k in 0..n {
+                let i = (first + k) % n;
+                match self.sources[i].poll() {
+                    Ok(()) => return Some(i),
+                    Err((f, val)) => {
+                        #[cfg(target_os = "linux")] { ws[i] = WaitV::new(f, val); }
+                        if park.is_none() { park = Some((f, val)) };
+                    },
+                }
+            }
+            #[cfg(target_os = "linux")] {
+                let r = match deadline {
+                    None => futex::wait_any(&ws[..n]),
+                    Some(t) => futex::wait_any_until(&ws[..n], t),
+                };
+                match r {
+                    Ok(i) => { first = i; continue },
+                    Err(FutexError::TimedOut) => return None,
+                    Err(FutexError::Other(ENOSYS)) => (),
+                    Err(_) => continue,
+                }
+            }
+            if deadline.is_some_and(|t| Instant::now() >= t) { return None };
+            if let Some((f, val)) = park { let _ = futex_wait_timeout(f, val, POLL); }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use core::sync::atomic::{AtomicU32, Ordering as Memord};
+
+use system::{futex_wait, futex_wake};
+use wait_any::Waitable;
+
+/// Count of outstanding tasks, which threads wait to reach zero
+///
+/// As a `Latch`, but the count may be raised as it goes, e.g. by `add(1)` before each task is
+/// spawned, and `done()` as each ends; and once at zero it may be raised again, for another
+/// batch.
+#[derive(Debug)]
+pub struct WaitGroup(AtomicU32);
+
+impl Default for WaitGroup {
+    #[inline] fn default() -> Self { WaitGroup::new() }
+}
+
+impl WaitGroup {
+    /// Make a wait group with no tasks outstanding.
+    #[inline] pub const fn new() -> Self { WaitGroup(AtomicU32::new(0)) }
+
+    /// Count `n` more tasks.
+    ///
+    /// This should happen before the tasks can call `done`, and, if the count may be zero,
+    /// before any `wait` which must wait for them, lest it return before they are counted.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if the count would overflow.
+    #[inline] pub fn add(&self, n: u32) {
+        let mut m = self.0.load(Memord::Relaxed);
+        loop {
+            let k = m.checked_add(n).expect("WaitGroup count overflowed");
+            match self.0.compare_exchange_weak(m, k, Memord::Relaxed, Memord::Relaxed) {
+                Ok(_) => break,
+                Err(old) => m = old,
+            }
+        }
+    }
+
+    /// Count one task done, unblocking all waiting threads if none are then outstanding.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if no task is outstanding.
+    #[inline] pub fn done(&self) {
+        let mut m = self.0.load(Memord::Relaxed);
+        loop {
+            assert_ne!(0, m, "WaitGroup done more often than added");
+            match self.0.compare_exchange_weak(m, m - 1, Memord::Release, Memord::Relaxed) {
+                Ok(_) => break,
+                Err(n) => m = n,
+            }
+        }
+        if 1 == m { futex_wake(&self.0, !0); }
+    }
+
+    /// Block until no task is outstanding.
+    #[inline] pub fn wait(&self) {
+        loop {
+            match self.0.load(Memord::Acquire) {
+                0 => return,
+                m => futex_wait(&self.0, m),
+            }
+        }
+    }
+
+    /// Number of tasks outstanding
+    ///
+    /// Other threads may add or finish tasks meanwhile, so this is only advisory.
+    #[inline] pub fn count(&self) -> u32 { self.0.load(Memord::Relaxed) }
+}
+
+impl Waitable for WaitGroup {
+    #[inline] fn poll(&self) -> Result<(), (&AtomicU32, u32)> {
+        match self.0.load(Memord::Acquire) { 0 => Ok(()), m => Err((&self.0, m)) }
+    }
+}
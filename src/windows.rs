@@ -0,0 +1,167 @@
+//! Windows system calls
+//!
+//! `WaitOnAddress` blocks while a word holds a value, and `WakeByAddressSingle` and
+//! `WakeByAddressAll` wake threads so blocked, as futexes do, so the futex backend runs on them
+//! as it is. They don't tell how many threads they woke, so each word counts its waiters in one
+//! of a few buckets, by which a wake reckons at most how many it woke; nor can they move
+//! waiters between words, nor be shared between processes.
+
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering as Memord};
+use core::time::Duration;
+
+#[link(name = "synchronization")]
+extern "system" {
+    fn WaitOnAddress(address: *const c_void, compare: *const c_void, size: usize,
+                     millis: u32) -> i32;
+    fn WakeByAddressSingle(address: *const c_void);
+    fn WakeByAddressAll(address: *const c_void);
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetLastError() -> u32;
+    fn GetCurrentThreadId() -> u32;
+    fn SwitchToThread() -> i32;
+    fn QueryPerformanceCounter(count: *mut i64) -> i32;
+    fn QueryPerformanceFrequency(frequency: *mut i64) -> i32;
+}
+
+const INFINITE: u32 = !0;
+const ERROR_TIMEOUT: u32 = 1460;
+
+const N_BUCKETS: usize = 64;
+
+// Threads blocked on the words of each bucket
+static WAITERS: [AtomicUsize; N_BUCKETS] = [const { AtomicUsize::new(0) }; N_BUCKETS];
+
+#[inline] fn waiters(f: &AtomicU32) -> &'static AtomicUsize {
+    &WAITERS[f as *const _ as usize / 4 % N_BUCKETS]
+}
+
+#[inline] fn addr(f: &AtomicU32) -> *const c_void { f as *const _ as *const c_void }
+
+// Block while `*f == val`, for at most `millis`; return `false` if it timed out.
+#[inline] fn wait(f: &AtomicU32, val: u32, millis: u32) -> Result<bool, u32> {
+    let n = waiters(f);
+    n.fetch_add(1, Memord::Relaxed);
+    let r = unsafe { WaitOnAddress(addr(f), &val as *const u32 as *const c_void, 4, millis) };
+    let e = if 0 == r { unsafe { GetLastError() } } else { 0 };
+    n.fetch_sub(1, Memord::Relaxed);
+    match (r, e) {
+        (0, ERROR_TIMEOUT) => Ok(false),
+        (0, e) => Err(e),
+        _ => Ok(true),
+    }
+}
+
+/// Identifier of the calling thread
+#[inline]
+pub fn gettid() -> u32 { unsafe { GetCurrentThreadId() } }
+
+/// Let another thread run.
+#[inline]
+pub fn yield_now() { unsafe { SwitchToThread(); } }
+
+/// Nanoseconds on the monotonic clock
+#[inline]
+pub fn monotonic_now() -> u64 {
+    let (mut count, mut frequency) = (0, 0);
+    unsafe {
+        QueryPerformanceCounter(&mut count);
+        QueryPerformanceFrequency(&mut frequency);
+    }
+    let (count, frequency) = (count as u64, frequency as u64);
+    count / frequency * 1_000_000_000 + count % frequency * 1_000_000_000 / frequency
+}
+
+/// Block while `*f == val`.
+///
+/// As on Linux, may return spuriously, so the caller must recheck its condition.
+#[inline]
+pub fn futex_wait(f: &AtomicU32, val: u32) {
+    let r = wait(f, val, INFINITE);
+    debug_assert!(r.is_ok(), "WaitOnAddress failed: error {:?}", r);
+}
+
+/// Block while `*f == val`, for at most `timeout`; return `false` if it timed out.
+#[inline]
+pub fn futex_wait_timeout(f: &AtomicU32, val: u32, timeout: Duration) -> Result<bool, u32> {
+    let nanos = timeout.as_secs().saturating_mul(1_000_000_000)
+                       .saturating_add(timeout.subsec_nanos() as u64);
+    futex_wait_until(f, val, monotonic_now().saturating_add(nanos))
+}
+
+/// As `futex_wait`; no signal interrupts a wait on Windows, so this returns `true`.
+#[inline]
+pub fn futex_wait_interruptible(f: &AtomicU32, val: u32) -> bool { futex_wait(f, val); true }
+
+/// Block while `*f == val`, until `deadline`, in nanoseconds on the monotonic clock.
+///
+/// Returns `Ok(false)` if the deadline passed, else as `futex_wait`. The wait is timed in whole
+/// milliseconds, rounded up, so it may overrun the deadline by as much, but never ends early.
+#[inline]
+pub fn futex_wait_until(f: &AtomicU32, val: u32, deadline: u64) -> Result<bool, u32> {
+    loop {
+        let now = monotonic_now();
+        if now >= deadline { return Ok(false) };
+        let millis = ((deadline - now - 1) / 1_000_000 + 1).min(INFINITE as u64 - 1) as u32;
+        if wait(f, val, millis)? { return Ok(true) };
+    }
+}
+
+// A timed wait of the crate's own primitives which failed would fail again if retried, and is
+// no timeout, so panic.
+#[cold]
+pub(crate) fn wait_failed(e: u32) -> ! { panic!("WaitOnAddress failed: error {}", e) }
+
+// Waits are never shared between processes, so the shared waits and wakes are private, but for
+// those of the primitives which only Linux can share.
+#[inline]
+pub(crate) fn futex_wait_shared(f: &AtomicU32, val: u32) { futex_wait(f, val) }
+
+#[inline]
+pub(crate) fn futex_wait_until_shared(f: &AtomicU32, val: u32,
+                                      deadline: u64) -> Result<bool, u32> {
+    futex_wait_until(f, val, deadline)
+}
+
+#[inline]
+pub(crate) fn futex_wake_shared(f: &AtomicU32, n: usize) -> usize { futex_wake(f, n) }
+
+/// Unblock at most `n` threads waiting on `f`; return at most how many were unblocked.
+///
+/// This wakes them all if no more than `n` may be waiting, else one at a time.
+#[inline]
+pub fn futex_wake(f: &AtomicU32, n: usize) -> usize {
+    let k = waiters(f).load(Memord::Relaxed);
+    if n > 1 && n >= k {
+        unsafe { WakeByAddressAll(addr(f)) };
+    } else {
+        for _ in 0..n { unsafe { WakeByAddressSingle(addr(f)) } }
+    }
+    k.min(n)
+}
+
+// Store `val` in `f`, and unblock a thread waiting on it; the wake only keys the waiter by the
+// address of `f`, so it needn't be valid by then.
+#[inline]
+pub(crate) fn futex_store_wake(f: &AtomicU32, val: u32) {
+    let p = addr(f);
+    f.store(val, Memord::Release);
+    unsafe { WakeByAddressSingle(p) };
+}
+
+// Unblock a thread waiting on each of `f1` and `f2`; return at most how many were unblocked.
+#[inline]
+pub(crate) fn futex_wake_both(f1: &AtomicU32, f2: &AtomicU32) -> usize {
+    futex_wake(f1, 1) + futex_wake(f2, 1)
+}
+
+// Lacking a requeue, unblock the threads rather than move them; they then block on `f2` as they
+// take it, if they must. As none is moved, it needn't compare `*f1` to `val`.
+#[inline]
+pub(crate) fn cmp_requeue(f1: &AtomicU32, _val: u32, n_wake: usize,
+                          _f2: *const AtomicU32, n_requeue: usize) -> Option<usize> {
+    Some(futex_wake(f1, n_wake.saturating_add(n_requeue)))
+}
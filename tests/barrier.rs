@@ -0,0 +1,34 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as Memord};
+use std::thread;
+
+use user_sync::Barrier;
+
+const N_THREADS: usize = 4;
+const N_ROUNDS: usize = 10_000;
+
+// Threads wait on one barrier in a tight loop, so those released from one phase arrive for the
+// next while the last may still be releasing the rest; none may pass a phase before all arrive,
+// nor fall a phase behind, and each phase has exactly one leader.
+#[test]
+fn reused_in_tight_loop() {
+    let barrier = Arc::new(Barrier::new(N_THREADS));
+    let n_arrived = Arc::new(AtomicUsize::new(0));
+    let n_leaders = Arc::new(AtomicUsize::new(0));
+    let threads: Vec<_> = (0..N_THREADS).map(|_| {
+        let (barrier, n_arrived, n_leaders) = (barrier.clone(), n_arrived.clone(),
+                                               n_leaders.clone());
+        thread::spawn(move || for k in 1..N_ROUNDS + 1 {
+            n_arrived.fetch_add(1, Memord::SeqCst);
+            if barrier.wait() { n_leaders.fetch_add(1, Memord::Relaxed); }
+            let n = n_arrived.load(Memord::SeqCst);
+            assert!(k * N_THREADS <= n && n < (k + 1) * N_THREADS, "round {}: {} arrived", k, n);
+        })
+    }).collect();
+    for t in threads { t.join().unwrap() }
+    assert_eq!(N_ROUNDS, n_leaders.load(Memord::Relaxed));
+}
@@ -0,0 +1,69 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+
+use user_sync::CombiningMutex;
+
+const N_OPS: usize = 2_000;
+
+// Each operation returns the count it made; they must be exactly 1 to the number of operations,
+// each once, however they were combined. More threads than slots make some take the lock
+// themselves.
+fn count_exactly_once(n_threads: usize) {
+    let m = Arc::new(CombiningMutex::new((0usize, Vec::new())));
+    let threads: Vec<_> = (0..n_threads).map(|k| {
+        let m = m.clone();
+        thread::spawn(move || {
+            (0..N_OPS).map(|i| m.apply(move |&mut (ref mut n, ref mut log): &mut (_, Vec<_>)| {
+                *n += 1;
+                log.push((k, i));
+                *n
+            })).collect::<Vec<_>>()
+        })
+    }).collect();
+    let mut counts: Vec<usize> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+    counts.sort();
+    assert!(counts.iter().cloned().eq(1..n_threads * N_OPS + 1));
+
+    // Each thread's operations ran in the order it applied them.
+    let log = m.apply(|&mut (_, ref mut log)| log.clone());
+    let mut next = vec![0; n_threads];
+    for (k, i) in log {
+        assert_eq!(next[k], i, "thread {}'s operations ran out of order", k);
+        next[k] += 1;
+    }
+}
+
+#[test]
+fn few_threads() { count_exactly_once(4) }
+
+#[test]
+fn more_threads_than_slots() { count_exactly_once(0x60) }
+
+// A panicking operation panicks its own thread, and the combiner too if another ran it, whose
+// own operation may then not run; every operation whose `apply` returned ran once, none ran
+// twice, and the lock stays usable.
+#[test]
+fn panic_in_operation() {
+    let m = Arc::new(CombiningMutex::new(0));
+    let threads: Vec<_> = (0..8).map(|k| {
+        let m = m.clone();
+        thread::spawn(move || {
+            (0..500).filter(|&i| {
+                let r = panic::catch_unwind(AssertUnwindSafe(|| m.apply(move |n| {
+                    if 0 == (k + i) % 7 { panic!("operation panicked") }
+                    *n += 1;
+                })));
+                r.is_ok()
+            }).count()
+        })
+    }).collect();
+    let n_ok: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+    let n_run = m.apply(|n| *n);
+    assert!(n_ok <= n_run);
+    assert!(n_run <= (0..8).flat_map(|k| (0..500).filter(move |i| 0 != (k + i) % 7)).count());
+}
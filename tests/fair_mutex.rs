@@ -0,0 +1,52 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::sync::{Arc, Mutex as StdMutex};
+use std::thread;
+use std::time::Duration;
+
+use user_sync::FairMutex;
+
+const N_THREADS: usize = 8;
+
+// Threads queue for the lock one after another while it is held, and the holder, once it
+// releases it, asks for it again at once; each must take it in the order it asked.
+#[test]
+fn granted_in_order_of_request() {
+    let mutex = Arc::new(FairMutex::new(()));
+    let order = Arc::new(StdMutex::new(Vec::new()));
+    let guard = mutex.lock();
+    let threads: Vec<_> = (0..N_THREADS).map(|k| {
+        let (mutex, order) = (mutex.clone(), order.clone());
+        let t = thread::spawn(move || {
+            let _guard = mutex.lock();
+            order.lock().unwrap().push(k);
+        });
+        thread::sleep(Duration::from_millis(50));
+        t
+    }).collect();
+    drop(guard);
+    drop(mutex.lock());
+    order.lock().unwrap().push(N_THREADS);
+    for t in threads { t.join().unwrap() }
+    assert_eq!((0..N_THREADS + 1).collect::<Vec<_>>(), *order.lock().unwrap());
+}
+
+// Under contention, by lock and try_lock, no increment may be lost.
+#[test]
+fn excludes() {
+    const N_ITERS: usize = 10000;
+    let mutex = Arc::new(FairMutex::new(0));
+    let threads: Vec<_> = (0..N_THREADS).map(|k| {
+        let mutex = mutex.clone();
+        thread::spawn(move || for _ in 0..N_ITERS {
+            let mut guard = if 0 == k % 2 { mutex.lock() } else {
+                loop { if let Some(guard) = mutex.try_lock() { break guard } }
+            };
+            *guard += 1;
+        })
+    }).collect();
+    for t in threads { t.join().unwrap() }
+    assert_eq!(N_THREADS * N_ITERS, *mutex.lock());
+}
@@ -0,0 +1,59 @@
+#![cfg(all(target_os = "linux", not(feature = "critical-section")))]
+
+extern crate user_sync;
+
+use std::os::unix::thread::JoinHandleExt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as Memord};
+use std::thread;
+use std::time::Duration;
+
+use user_sync::{CondVar, Mutex};
+
+const SIGUSR1: i32 = 10;
+
+extern "C" {
+    fn signal(sig: i32, handler: extern "C" fn(i32)) -> usize;
+    fn siginterrupt(sig: i32, flag: i32) -> i32;
+    fn pthread_kill(thread: u64, sig: i32) -> i32;
+}
+
+static N_SIGNALS: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_signal(_: i32) { N_SIGNALS.fetch_add(1, Memord::Relaxed); }
+
+// A waiter parked on the condition variable, never notified, must return `Err` when a signal
+// is delivered to it, holding the lock again.
+#[test]
+fn signal_interrupts_parked_waiter() {
+    unsafe {
+        signal(SIGUSR1, on_signal);
+        // Else the kernel restarts the wait after the handler.
+        siginterrupt(SIGUSR1, 1);
+    }
+
+    let shared = Arc::new((Mutex::new(false), CondVar::new(), AtomicBool::new(false)));
+    let shared_ = shared.clone();
+    let waiter = thread::spawn(move || {
+        let (ref m, ref c, ref done) = *shared_;
+        let mut guard = m.lock();
+        *guard = true;
+        let r = c.wait_interruptible(guard);
+        done.store(true, Memord::Release);
+        match r {
+            Ok(_) => panic!("wait returned without a signal nor a notification"),
+            Err(guard) => assert!(*guard.0),
+        }
+    });
+
+    // Once the flag is seen under the lock, the waiter released the lock as it began to wait;
+    // signal it until its wait returns, in case the first signal comes before it parks.
+    while !*shared.0.lock() { thread::yield_now() }
+    let tid = waiter.as_pthread_t() as u64;
+    while !shared.2.load(Memord::Acquire) {
+        unsafe { pthread_kill(tid, SIGUSR1) };
+        thread::sleep(Duration::from_millis(10));
+    }
+    waiter.join().unwrap();
+    assert!(N_SIGNALS.load(Memord::Relaxed) > 0);
+}
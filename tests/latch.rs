@@ -0,0 +1,38 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as Memord};
+use std::thread;
+
+use user_sync::Latch;
+
+const N_THREADS: usize = 8;
+const N_ROUNDS: usize = 1_000;
+
+// Threads count down by various amounts at once, jointly overshooting zero; exactly one of them
+// must see its call take the count there, and the waiters must all be released.
+#[test]
+fn concurrent_decrements_reach_zero_once() {
+    for _ in 0..N_ROUNDS {
+        let latch = Arc::new(Latch::new((N_THREADS * (N_THREADS + 1) / 2 - 3) as u32));
+        let n_zeroed = Arc::new(AtomicUsize::new(0));
+        let waiters: Vec<_> = (0..2).map(|_| {
+            let latch = latch.clone();
+            thread::spawn(move || latch.wait())
+        }).collect();
+        let counters: Vec<_> = (1..N_THREADS + 1).map(|k| {
+            let (latch, n_zeroed) = (latch.clone(), n_zeroed.clone());
+            thread::spawn(move || {
+                let zeroed = if 1 == k { latch.count_down() }
+                             else { latch.count_down_by(k as u32) };
+                if zeroed { n_zeroed.fetch_add(1, Memord::Relaxed); }
+            })
+        }).collect();
+        for t in counters.into_iter().chain(waiters) { t.join().unwrap() }
+        assert_eq!(1, n_zeroed.load(Memord::Relaxed));
+        assert!(latch.is_open());
+        assert!(!latch.count_down_by(1));
+    }
+}
@@ -0,0 +1,17 @@
+#![cfg(feature = "named-locks")]
+
+extern crate user_sync;
+
+use user_sync::{dump_locks, Mutex};
+
+static NAMED: Mutex<u32> = Mutex::new_named(0, "named");
+
+#[test]
+fn register_twice_then_dump() {
+    NAMED.register();
+    NAMED.register();
+    let guard = NAMED.lock();
+    dump_locks();
+    drop(guard);
+    dump_locks();
+}
@@ -0,0 +1,65 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use user_sync::{CondVar, Mutex};
+
+const K: usize = 8;
+const N_ROUNDS: usize = 1_000;
+
+struct Queue {
+    items: Vec<usize>,
+    n_taken: usize,
+    done: bool,
+}
+
+// A producer pushes `K` items at once and wakes `K` of twice as many consumers by `notify_n`;
+// each consumer rechecks the queue as it wakes, by `wait_while`. The queue must drain every
+// round, else a wakeup was lost.
+fn k_items_k_consumers(cond: CondVar) {
+    let shared = Arc::new((Mutex::new(Queue { items: Vec::new(), n_taken: 0, done: false }),
+                           cond, CondVar::new()));
+    let consumers: Vec<_> = (0..2 * K).map(|_| {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            let (ref queue, ref nonempty, ref drained) = *shared;
+            let mut n = 0;
+            loop {
+                let mut q = nonempty.wait_while(queue.lock(), |q| q.items.is_empty() && !q.done);
+                match q.items.pop() {
+                    Some(_) => { n += 1; q.n_taken += 1; },
+                    None => return n,
+                }
+                if q.items.is_empty() { drained.notify_one(); }
+            }
+        })
+    }).collect();
+
+    let (ref queue, ref nonempty, ref drained) = *shared;
+    for round in 0..N_ROUNDS {
+        {
+            let mut q = queue.lock();
+            q.items.extend(0..K);
+        }
+        assert!(nonempty.notify_n(K) <= K);
+        let q = queue.lock();
+        let (q, timed_out) = drained.wait_timeout_while(q, Duration::from_secs(10),
+                                                       |q| !q.items.is_empty());
+        assert!(!timed_out, "round {}: {} items left undrained", round, q.items.len());
+    }
+    queue.lock().done = true;
+    nonempty.notify_all();
+    let n: usize = consumers.into_iter().map(|t| t.join().unwrap()).sum();
+    assert_eq!(K * N_ROUNDS, n);
+    assert_eq!(K * N_ROUNDS, queue.lock().n_taken);
+}
+
+#[test]
+fn k_items_k_consumers_requeuing() { k_items_k_consumers(CondVar::new()) }
+
+#[test]
+fn k_items_k_consumers_coalescing() { k_items_k_consumers(CondVar::coalescing()) }
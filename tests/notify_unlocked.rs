@@ -0,0 +1,62 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use user_sync::{CondVar, Mutex};
+
+const N_WAITERS: usize = 16;
+const N_EACH: usize = 2_000;
+
+struct State {
+    tokens: usize,
+    n_done: usize,
+}
+
+// Each token is made under the lock, and then notified with the lock released, so notifications
+// race the waiters' entering and leaving their waits. Were one lost, a waiter would sleep with a
+// token left for it, and the waiters would not all finish.
+fn every_waiter_finishes<F: Fn(&CondVar)>(cond: CondVar, notify: F) {
+    let shared = Arc::new((Mutex::new(State { tokens: 0, n_done: 0 }), cond, CondVar::new()));
+    let waiters: Vec<_> = (0..N_WAITERS).map(|_| {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            let (ref m, ref cond, ref done) = *shared;
+            for _ in 0..N_EACH {
+                let mut s = cond.wait_while(m.lock(), |s| 0 == s.tokens);
+                s.tokens -= 1;
+            }
+            m.lock().n_done += 1;
+            done.notify_one();
+        })
+    }).collect();
+
+    let (ref m, ref cond, ref done) = *shared;
+    for _ in 0..N_WAITERS * N_EACH {
+        m.lock().tokens += 1;
+        notify(cond);
+    }
+    let (s, timed_out) = done.wait_timeout_while(m.lock(), Duration::from_secs(30),
+                                                 |s| s.n_done < N_WAITERS);
+    assert!(!timed_out, "{} of {} waiters finished, with {} tokens left",
+            s.n_done, N_WAITERS, s.tokens);
+    drop(s);
+    for w in waiters { w.join().unwrap() }
+}
+
+#[test]
+fn notify_one() { every_waiter_finishes(CondVar::new(), |c| { c.notify_one(); }) }
+
+#[test]
+fn notify_one_coalescing() {
+    every_waiter_finishes(CondVar::coalescing(), |c| { c.notify_one(); })
+}
+
+#[test]
+fn notify_n() { every_waiter_finishes(CondVar::new(), |c| { c.notify_n(1); }) }
+
+#[test]
+fn notify_n_coalescing() { every_waiter_finishes(CondVar::coalescing(), |c| { c.notify_n(1); }) }
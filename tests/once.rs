@@ -0,0 +1,67 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as Memord};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use user_sync::{Latch, Once, OnceResult};
+
+// A waiter gives up on a slow initializer, which yet runs to completion in its own thread.
+#[test]
+fn waiter_times_out_while_initializer_runs() {
+    let shared = Arc::new((Once::new(), Latch::new(1), AtomicBool::new(false)));
+    let runner = {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            let (ref once, ref started, ref done) = *shared;
+            once.call_once(|| {
+                started.count_down();
+                thread::sleep(Duration::from_millis(500));
+                done.store(true, Memord::Relaxed);
+            });
+        })
+    };
+
+    let (ref once, ref started, ref done) = *shared;
+    started.wait();
+    let t = Instant::now();
+    let r = once.call_once_timeout(Duration::from_millis(50), || panic!("ran a second closure"));
+    assert_eq!(OnceResult::TimedOut, r);
+    assert!(t.elapsed() >= Duration::from_millis(50));
+    assert!(!once.is_completed());
+
+    runner.join().unwrap();
+    assert!(done.load(Memord::Relaxed));
+    assert!(once.is_completed());
+    assert_eq!(OnceResult::Completed,
+               once.call_once_timeout(Duration::from_millis(50), || panic!("ran again")));
+}
+
+#[test]
+fn waiter_sees_initializer_complete_in_time() {
+    let once = Arc::new(Once::new());
+    let started = Arc::new(Latch::new(1));
+    let runner = {
+        let (once, started) = (once.clone(), started.clone());
+        thread::spawn(move || once.call_once(|| {
+            started.count_down();
+            thread::sleep(Duration::from_millis(50));
+        }))
+    };
+    started.wait();
+    assert_eq!(OnceResult::Completed,
+               once.call_once_timeout(Duration::from_secs(10), || panic!("ran a second closure")));
+    runner.join().unwrap();
+}
+
+#[test]
+fn caller_runs_its_closure() {
+    let once = Once::new();
+    let mut ran = false;
+    assert_eq!(OnceResult::Ran, once.call_once_timeout(Duration::from_millis(0), || ran = true));
+    assert!(ran);
+    assert!(once.is_completed());
+}
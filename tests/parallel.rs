@@ -0,0 +1,36 @@
+#![cfg(feature = "std")]
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as Memord};
+
+use user_sync::parallel_for;
+
+// Each index is called exactly once, however the range divides among the threads.
+#[test]
+fn each_index_once() {
+    for &(len, n_threads) in &[(0, 3), (1, 4), (7, 3), (100, 7), (5, 16)] {
+        let counts = Arc::new((0..len).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>());
+        let counts_ = counts.clone();
+        parallel_for(0..len, n_threads, move |i| { counts_[i].fetch_add(1, Memord::Relaxed); });
+        assert!(counts.iter().all(|n| 1 == n.load(Memord::Relaxed)), "{} in {}", len, n_threads);
+    }
+}
+
+#[test]
+fn range_at_end_of_indices() {
+    let n = Arc::new(AtomicUsize::new(0));
+    let n_ = n.clone();
+    parallel_for(!0 - 10..!0, 4, move |i| {
+        assert!(i >= !0 - 10);
+        n_.fetch_add(1, Memord::Relaxed);
+    });
+    assert_eq!(10, n.load(Memord::Relaxed));
+}
+
+#[test]
+#[should_panic(expected = "index 42")]
+fn panic_reaches_caller() {
+    parallel_for(0..100, 4, |i| if 42 == i { panic!("index 42") });
+}
@@ -0,0 +1,20 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use user_sync::{Backoff, Mutex, SpinPolicy, set_default_policy};
+
+// A thread which spins not at all must still take a free lock as uncontended, not mark it
+// contended and so have its unlock wake none.
+#[test]
+fn uncontended_lock_without_spins() {
+    set_default_policy(SpinPolicy::PARK);
+    let mutex = Mutex::new(0);
+    {
+        let _guard = mutex.lock();
+        assert!(!mutex.is_contended());
+    }
+    let mutex = Mutex::with_backoff(0, Backoff::None);
+    let _guard = mutex.lock();
+    assert!(!mutex.is_contended());
+}
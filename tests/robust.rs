@@ -0,0 +1,50 @@
+#![cfg(all(target_os = "linux", feature = "robust", not(feature = "critical-section")))]
+
+extern crate user_sync;
+
+use std::{mem, ptr};
+
+use user_sync::{OwnerDied, RobustMutex};
+
+const PROT_READ: i32 = 1;
+const PROT_WRITE: i32 = 2;
+const MAP_SHARED: i32 = 1;
+const MAP_ANONYMOUS: i32 = 0x20;
+
+extern "C" {
+    fn mmap(addr: *mut u8, len: usize, prot: i32, flags: i32, fd: i32, off: i64) -> *mut u8;
+    fn fork() -> i32;
+    fn waitpid(pid: i32, status: *mut i32, options: i32) -> i32;
+    fn _exit(status: i32) -> !;
+}
+
+// The parent's thread registers its robust list, and knows its id, before it forks; the child
+// must register its own, and lock by its own id, for the kernel to release the lock as it dies.
+#[test]
+fn child_of_fork_dies_holding_lock() {
+    let mutex = unsafe {
+        let p = mmap(ptr::null_mut(), mem::size_of::<RobustMutex<u32>>(), PROT_READ | PROT_WRITE,
+                     MAP_SHARED | MAP_ANONYMOUS, -1, 0) as *mut RobustMutex<u32>;
+        assert!(-1 != p as isize);
+        ptr::write(p, RobustMutex::new(0));
+        &*p
+    };
+    *mutex.lock().ok().unwrap() = 1;
+    match unsafe { fork() } {
+        0 => unsafe {
+            mem::forget(mutex.lock());
+            _exit(0)
+        },
+        pid => {
+            assert!(pid > 0);
+            let mut status = -1;
+            assert_eq!(pid, unsafe { waitpid(pid, &mut status, 0) });
+            assert_eq!(0, status);
+        },
+    }
+    match mutex.try_lock() {
+        Some(Err(OwnerDied(guard))) => assert_eq!(1, *guard),
+        Some(Ok(_)) => panic!("RobustMutex reported its owner lived, though it died holding it"),
+        None => panic!("RobustMutex still held by the child, though it died"),
+    }
+}
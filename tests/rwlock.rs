@@ -0,0 +1,94 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::thread;
+
+use user_sync::RwLock;
+
+// Readers in different threads share the lock.
+#[test]
+fn read_concurrently() {
+    let lock = Arc::new(RwLock::new(0));
+    let a = lock.read();
+    let b = { let lock = lock.clone(); thread::spawn(move || *lock.read()) }.join().unwrap();
+    assert_eq!(*a, b);
+    drop(a);
+    *lock.write() += 1;
+    assert_eq!(1, *lock.read());
+}
+
+#[test]
+fn relock_after_release() {
+    let lock = RwLock::new(0);
+    drop(lock.read());
+    drop(lock.write());
+    drop(lock.try_read().unwrap());
+    drop(lock.try_write().unwrap());
+    drop(lock.write());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "holds it for reading")]
+fn write_while_reading() {
+    let lock = RwLock::new(0);
+    let _a = lock.read();
+    let _b = lock.write();
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "holds it for writing")]
+fn read_while_writing() {
+    let lock = RwLock::new(0);
+    let _a = lock.write();
+    let _b = lock.read();
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "holds it for writing")]
+fn write_while_writing() {
+    let lock = RwLock::new(0);
+    let _a = lock.try_write().unwrap();
+    let _b = lock.write();
+}
+
+// A second read may wait on a writer waiting on the first, so it is refused as well.
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "holds it for reading")]
+fn read_while_reading() {
+    let lock = RwLock::new(0);
+    let _a = lock.read();
+    let _b = lock.read();
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "holds it for reading")]
+fn try_read_while_reading() {
+    let lock = RwLock::new(0);
+    let _a = lock.try_read().unwrap();
+    let _b = lock.try_read();
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "holds it for reading")]
+fn try_write_while_reading() {
+    let lock = RwLock::new(0);
+    let _a = lock.read();
+    let _b = lock.try_write();
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "holds it for writing")]
+fn try_read_while_writing() {
+    let lock = RwLock::new(0);
+    let _a = lock.write();
+    let _b = lock.try_read();
+}
@@ -0,0 +1,41 @@
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as Memord};
+use std::thread;
+
+use user_sync::SeqLock;
+
+const N_WRITERS: usize = 4;
+const N_WRITES: u64 = 10_000;
+
+// Every write keeps the words equal, so a reader which ever sees them unequal saw a torn write.
+#[test]
+fn writers_and_hot_reader() {
+    let lock = Arc::new(SeqLock::new([0u64; 8]));
+    let n_done = Arc::new(AtomicUsize::new(0));
+
+    let writers: Vec<_> = (0..N_WRITERS).map(|_| {
+        let (lock, n_done) = (lock.clone(), n_done.clone());
+        thread::spawn(move || {
+            for _ in 0..N_WRITES {
+                for x in lock.write().iter_mut() { *x += 1 }
+            }
+            n_done.fetch_add(1, Memord::Release);
+        })
+    }).collect();
+
+    let mut last = 0;
+    let mut n_reads = 0u64;
+    while n_done.load(Memord::Acquire) < N_WRITERS {
+        let xs = lock.read();
+        assert!(xs.iter().all(|&x| x == xs[0]), "torn read: {:?}", xs);
+        assert!(xs[0] >= last, "read went back from {} to {}", last, xs[0]);
+        last = xs[0];
+        n_reads += 1;
+    }
+    for w in writers { w.join().unwrap() }
+
+    assert!(n_reads > 0);
+    assert_eq!([N_WRITERS as u64 * N_WRITES; 8], lock.read());
+}
@@ -0,0 +1,30 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::panic;
+use std::thread;
+
+use user_sync::spsc::Ring;
+
+const N_VALUES: u32 = 100_000;
+
+// A length which doesn't divide 2^32 would put the value pushed as the tail wraps in another
+// slot than that it is popped from, so it is refused.
+#[test]
+fn refuses_length_not_power_of_two() {
+    assert!(panic::catch_unwind(|| { Ring::new(&mut [None::<u32>; 3]); }).is_err());
+    assert!(panic::catch_unwind(|| { Ring::new(&mut [None::<u32>; 0]); }).is_err());
+}
+
+// Values come out in order, whether the producer or the consumer runs ahead.
+#[test]
+fn values_in_order() {
+    let mut buf = [None; 8];
+    let mut ring = Ring::new(&mut buf);
+    let (mut tx, mut rx) = ring.split();
+    thread::scope(|s| {
+        s.spawn(move || for k in 0..N_VALUES { tx.push(k) });
+        for k in 0..N_VALUES { assert_eq!(k, rx.pop()) }
+    });
+}
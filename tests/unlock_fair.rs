@@ -0,0 +1,63 @@
+extern crate user_sync;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use user_sync::{Backoff, Guard, Mutex};
+
+// Have a thread take the lock after parking for it, so the lock is marked contended though no
+// other thread waits, and run `f` with the guard.
+fn contended_without_waiters<F: FnOnce(&Mutex<u32>, Guard<u32>) + Send + 'static>(f: F) {
+    let mutex = Arc::new(Mutex::new(0));
+    let guard = mutex.lock();
+    let t = {
+        let mutex = mutex.clone();
+        thread::spawn(move || {
+            let guard = mutex.lock();
+            assert!(mutex.is_contended());
+            f(&mutex, guard);
+        })
+    };
+    thread::sleep(Duration::from_millis(50));
+    drop(guard);
+    t.join().unwrap();
+}
+
+// With none to hand it to, the lock must be left free, not handed off to none.
+#[test]
+fn unlock_fair_without_waiters_frees() {
+    contended_without_waiters(|mutex, guard| {
+        Guard::unlock_fair(guard);
+        assert!(mutex.try_lock().is_some());
+    });
+}
+
+#[test]
+fn bump_without_waiters_keeps_lock() {
+    contended_without_waiters(|mutex, mut guard| {
+        Guard::bump(&mut guard);
+        *guard += 1;
+        assert!(mutex.try_lock().is_none());
+        drop(guard);
+        assert_eq!(Some(1), mutex.try_lock().map(|g| *g));
+    });
+}
+
+// A thread taking the lock afresh as it is handed off must wait for the waiter it went to, even
+// trying it but once before it parks.
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn unlock_fair_not_barged() {
+    let mutex = Arc::new(Mutex::with_backoff(Vec::new(), Backoff::None));
+    let guard = mutex.lock();
+    let t = {
+        let mutex = mutex.clone();
+        thread::spawn(move || mutex.lock().push(1))
+    };
+    thread::sleep(Duration::from_millis(50));
+    Guard::unlock_fair(guard);
+    mutex.lock().push(2);
+    t.join().unwrap();
+    assert_eq!(vec![1, 2], *mutex.lock());
+}
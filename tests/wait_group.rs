@@ -0,0 +1,47 @@
+#![cfg(not(feature = "critical-section"))]
+
+extern crate user_sync;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as Memord};
+use std::thread;
+
+use user_sync::WaitGroup;
+
+const N_TASKS: usize = 8;
+const N_ROUNDS: usize = 1_000;
+
+// An add which would overflow must panic without touching the count, so that the tasks counted
+// already can still finish and release the waiters.
+#[test]
+fn overflowing_add_leaves_count() {
+    let group = WaitGroup::new();
+    group.add(2);
+    let r = panic::catch_unwind(AssertUnwindSafe(|| group.add(!0)));
+    assert!(r.is_err());
+    assert_eq!(2, group.count());
+    group.done();
+    group.done();
+    group.wait();
+}
+
+// One batch after another, on one group: each wait must return only once its batch is done.
+#[test]
+fn batches_reuse_group() {
+    let group = Arc::new(WaitGroup::new());
+    let n_done = Arc::new(AtomicUsize::new(0));
+    for k in 1..N_ROUNDS + 1 {
+        group.add(N_TASKS as u32);
+        let tasks: Vec<_> = (0..N_TASKS).map(|_| {
+            let (group, n_done) = (group.clone(), n_done.clone());
+            thread::spawn(move || {
+                n_done.fetch_add(1, Memord::Relaxed);
+                group.done();
+            })
+        }).collect();
+        group.wait();
+        assert_eq!(k * N_TASKS, n_done.load(Memord::Relaxed));
+        for t in tasks { t.join().unwrap() }
+    }
+}